@@ -12,8 +12,8 @@ pub fn acknowledge(ui: &Ui, attribution: Attribution) {
         .anchor(Align2::LEFT_TOP, [10., 10.])
         .show(ui.ctx(), |ui| {
             ui.horizontal(|ui| {
-                if let Some(logo) = attribution.logo_light {
-                    ui.add(egui::Image::new(logo).max_height(30.0).max_width(80.0));
+                if let Some(logo) = attribution.logo(ui.visuals(), 30.0) {
+                    ui.add(logo.max_width(80.0));
                 }
                 ui.hyperlink_to(attribution.text, attribution.url);
             });
@@ -1,27 +1,41 @@
 #![doc = include_str!("../README.md")]
 
-use http_body_util::Full;
+use http_body_util::{combinators::BoxBody, BodyExt, Full};
 use hyper::server::conn::http1;
 use hyper_util::rt::TokioIo;
 use std::{
     collections::HashMap,
+    convert::Infallible,
     future::Future,
     net::SocketAddr,
     pin::Pin,
     sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
 };
 use tokio::net::TcpListener;
-use tokio::sync::oneshot;
+use tokio::sync::{mpsc, oneshot};
 
 pub use hyper;
 pub use hyper::body::Bytes;
 
-type Response = hyper::Response<Full<Bytes>>;
+type Response = hyper::Response<BoxBody<Bytes, Infallible>>;
 pub type StatusCode = hyper::StatusCode;
 
 /// Request that already came.
 type HyperRequest = hyper::Request<hyper::body::Incoming>;
 
+/// Request that already came, with its body fully read. Returned by
+/// [`AnticipatedRequest::expect_full`].
+#[derive(Debug)]
+pub struct RecordedRequest {
+    pub method: hyper::Method,
+    pub path: String,
+    pub query: Option<String>,
+    pub headers: hyper::HeaderMap,
+    pub body: Bytes,
+}
+
 struct Expectation {
     payload_rx: oneshot::Receiver<Response>,
     request_tx: oneshot::Sender<HyperRequest>,
@@ -34,13 +48,25 @@ struct State {
 
     /// Requests that were unexpected.
     unexpected: Vec<String>,
+
+    /// Anticipations that were already consumed by a request, and not anticipated again since.
+    consumed: Vec<String>,
+
+    /// Set by [`Server::default_response`].
+    default_responder: Option<DefaultResponder>,
 }
 
+type DefaultResponder = Box<dyn Fn(&str) -> Option<Bytes> + Send>;
+
 /// Central part of the library. All HTTP requests need to be anticipated, otherwise it will panic
 /// when dropped.
 pub struct Server {
     port: u16,
     state: Arc<Mutex<State>>,
+
+    /// Stops the accept loop, see [`Server::shutdown`].
+    shutdown_tx: Option<oneshot::Sender<()>>,
+    accept_loop: Option<tokio::task::JoinHandle<()>>,
 }
 
 impl Server {
@@ -51,11 +77,18 @@ impl Server {
         let addr = SocketAddr::from(([127, 0, 0, 1], 0));
         let listener = TcpListener::bind(addr).await.unwrap();
         let port = listener.local_addr().unwrap().port();
+        let (shutdown_tx, mut shutdown_rx) = oneshot::channel();
 
         let state_clone = state.clone();
-        tokio::spawn(async move {
+        let accept_loop = tokio::spawn(async move {
             loop {
-                let (stream, _) = listener.accept().await.unwrap();
+                let (stream, _) = tokio::select! {
+                    accepted = listener.accept() => accepted.unwrap(),
+                    _ = &mut shutdown_rx => {
+                        log::info!("Shutting down.");
+                        return;
+                    }
+                };
                 let io = TokioIo::new(stream);
 
                 let state = state_clone.clone();
@@ -68,7 +101,23 @@ impl Server {
             }
         });
 
-        Server { port, state }
+        Server {
+            port,
+            state,
+            shutdown_tx: Some(shutdown_tx),
+            accept_loop: Some(accept_loop),
+        }
+    }
+
+    /// Stop accepting new connections and release the port. Unexpected requests are still
+    /// checked when the server is dropped.
+    pub async fn shutdown(mut self) {
+        if let (Some(shutdown_tx), Some(accept_loop)) =
+            (self.shutdown_tx.take(), self.accept_loop.take())
+        {
+            let _ = shutdown_tx.send(());
+            accept_loop.await.unwrap();
+        }
     }
 
     /// Returns the port, which this server listens on.
@@ -82,10 +131,9 @@ impl Server {
         log::info!("Anticipating '{}'.", url);
         let (payload_tx, payload_rx) = oneshot::channel();
         let (request_tx, happened_rx) = oneshot::channel();
-        if self
-            .state
-            .lock()
-            .unwrap()
+        let mut state = self.state.lock().unwrap();
+        state.consumed.retain(|consumed| *consumed != url);
+        if state
             .expectations
             .insert(
                 url.to_owned(),
@@ -98,17 +146,38 @@ impl Server {
         {
             panic!("already anticipating");
         };
+        drop(state);
         AnticipatedRequest {
             url,
             payload_tx,
             request_rx: Some(happened_rx),
         }
     }
+
+    /// Respond to requests which were not anticipated, instead of treating them as unexpected.
+    /// The `responder` is given the request's path and returns the body to respond with, or
+    /// [`None`] if such request is still unexpected.
+    pub fn default_response(&self, responder: impl Fn(&str) -> Option<Bytes> + Send + 'static) {
+        self.state.lock().unwrap().default_responder = Some(Box::new(responder));
+    }
+
+    /// Panic if a request for the `url` came since it was last anticipated, or at all if it was
+    /// never anticipated. Useful for checking that something, e.g. a cached resource, is not
+    /// fetched again.
+    pub fn assert_not_requested(&self, url: &str) {
+        let state = self.state.lock().unwrap();
+        if state.consumed.iter().any(|consumed| consumed == url)
+            || state.unexpected.iter().any(|unexpected| unexpected == url)
+        {
+            panic!("'{}' was requested", url);
+        }
+    }
 }
 
 impl Drop for Server {
     fn drop(&mut self) {
-        if !self.state.lock().unwrap().unexpected.is_empty() {
+        // Do not panic again if the test is already failing, as it would abort the whole process.
+        if !std::thread::panicking() && !self.state.lock().unwrap().unexpected.is_empty() {
             panic!("there are unexpected requests");
         }
     }
@@ -130,7 +199,7 @@ impl AnticipatedRequest {
     pub async fn respond(self, payload: impl AsRef<[u8]>) {
         log::info!("Saving response for '{}'.", self.url);
         let payload: hyper::body::Bytes = payload.as_ref().to_owned().into();
-        let response = hyper::Response::new(Full::new(payload));
+        let response = hyper::Response::new(Full::new(payload).boxed());
         self.payload_tx.send(response).unwrap();
     }
 
@@ -143,11 +212,39 @@ impl AnticipatedRequest {
         );
         let response = hyper::Response::builder()
             .status(status)
-            .body(Full::new(Bytes::default()))
+            .body(Full::new(Bytes::default()).boxed())
             .unwrap();
         self.payload_tx.send(response).unwrap();
     }
 
+    /// Like [`AnticipatedRequest::respond`], but send the body in `chunks`, waiting for
+    /// `delay_between` them. Useful for testing how clients handle partial reads.
+    pub async fn respond_stream(
+        self,
+        chunks: impl IntoIterator<Item = Bytes>,
+        delay_between: Duration,
+    ) {
+        log::info!("Saving streamed response for '{}'.", self.url);
+        let chunks: Vec<Bytes> = chunks.into_iter().collect();
+        let (chunk_tx, chunk_rx) = mpsc::channel(1);
+
+        tokio::spawn(async move {
+            for (i, chunk) in chunks.into_iter().enumerate() {
+                if i > 0 {
+                    tokio::time::sleep(delay_between).await;
+                }
+
+                if chunk_tx.send(chunk).await.is_err() {
+                    log::warn!("Client stopped reading the streamed response.");
+                    return;
+                }
+            }
+        });
+
+        let response = hyper::Response::new(ChunkedBody { chunk_rx }.boxed());
+        self.payload_tx.send(response).unwrap();
+    }
+
     /// Expect the request to come, but do not respond to it yet.
     pub async fn expect(&mut self) -> HyperRequest {
         log::info!("Expecting '{}'.", self.url);
@@ -157,6 +254,38 @@ impl AnticipatedRequest {
             panic!("this request was already expected");
         }
     }
+
+    /// Like [`AnticipatedRequest::expect`], but also read the whole body of the request, so it
+    /// can be easily inspected.
+    pub async fn expect_full(&mut self) -> RecordedRequest {
+        let (parts, body) = self.expect().await.into_parts();
+        RecordedRequest {
+            method: parts.method,
+            path: parts.uri.path().to_owned(),
+            query: parts.uri.query().map(ToOwned::to_owned),
+            headers: parts.headers,
+            body: body.collect().await.unwrap().to_bytes(),
+        }
+    }
+}
+
+/// Body which is sent chunk by chunk, as they are received from the channel.
+struct ChunkedBody {
+    chunk_rx: mpsc::Receiver<Bytes>,
+}
+
+impl hyper::body::Body for ChunkedBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<hyper::body::Frame<Bytes>, Infallible>>> {
+        self.chunk_rx
+            .poll_recv(cx)
+            .map(|chunk| chunk.map(|chunk| Ok(hyper::body::Frame::data(chunk))))
+    }
 }
 
 struct Service {
@@ -164,7 +293,7 @@ struct Service {
 }
 
 impl hyper::service::Service<hyper::Request<hyper::body::Incoming>> for Service {
-    type Response = hyper::Response<Full<Bytes>>;
+    type Response = hyper::Response<BoxBody<Bytes, Infallible>>;
     type Error = hyper::Error;
     type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
 
@@ -172,11 +301,15 @@ impl hyper::service::Service<hyper::Request<hyper::body::Incoming>> for Service
         log::info!("Incoming request '{}'.", request.uri());
         let state = self.state.clone();
         Box::pin(async move {
-            let expectation = state
-                .lock()
-                .unwrap()
-                .expectations
-                .remove(request.uri().path());
+            let expectation = {
+                let mut state = state.lock().unwrap();
+                let path = request.uri().path();
+                let expectation = state.expectations.remove(path);
+                if expectation.is_some() {
+                    state.consumed.push(path.to_owned());
+                }
+                expectation
+            };
 
             if let Some(expectation) = expectation {
                 let uri = request.uri().to_owned();
@@ -203,15 +336,25 @@ impl hyper::service::Service<hyper::Request<hyper::body::Incoming>> for Service
                     }
                 }
             } else {
+                let mut state = state.lock().unwrap();
+                let default_response = state
+                    .default_responder
+                    .as_ref()
+                    .and_then(|responder| responder(request.uri().path()));
+
+                if let Some(payload) = default_response {
+                    log::info!(
+                        "Responding to '{}' with the default response.",
+                        request.uri()
+                    );
+                    return Ok(hyper::Response::new(Full::new(payload).boxed()));
+                }
+
                 log::warn!("Unexpected '{}'.", request.uri());
-                state
-                    .lock()
-                    .unwrap()
-                    .unexpected
-                    .push(request.uri().to_string());
+                state.unexpected.push(request.uri().to_string());
                 Ok(hyper::Response::builder()
                     .status(418)
-                    .body(Full::new(Bytes::from_static(b"unexpected")))
+                    .body(Full::new(Bytes::from_static(b"unexpected")).boxed())
                     .unwrap())
             }
         })
@@ -109,3 +109,140 @@ async fn can_not_expect_twice() {
     )
     .await;
 }
+
+#[tokio::test]
+async fn not_requested_since_anticipated_again() {
+    let _ = env_logger::try_init();
+
+    let mock = Server::bind().await;
+    let url = format!("http://localhost:{}/foo", mock.port());
+
+    mock.anticipate("/foo")
+        .await
+        .respond(Bytes::from_static(b"hello"))
+        .await;
+    reqwest::get(url).await.unwrap();
+
+    mock.assert_not_requested("/bar");
+
+    // Anticipating again starts from scratch.
+    let _request = mock.anticipate("/foo").await;
+    mock.assert_not_requested("/foo");
+}
+
+#[tokio::test]
+#[should_panic(expected = "'/foo' was requested")]
+async fn anticipated_request_was_requested() {
+    let _ = env_logger::try_init();
+
+    let mock = Server::bind().await;
+    let url = format!("http://localhost:{}/foo", mock.port());
+
+    mock.anticipate("/foo")
+        .await
+        .respond(Bytes::from_static(b"hello"))
+        .await;
+    reqwest::get(url).await.unwrap();
+
+    mock.assert_not_requested("/foo");
+}
+
+#[tokio::test]
+#[should_panic(expected = "'/foo' was requested")]
+async fn unanticipated_request_was_requested() {
+    let _ = env_logger::try_init();
+
+    let mock = Server::bind().await;
+    let url = format!("http://localhost:{}/foo", mock.port());
+
+    reqwest::get(url).await.unwrap();
+
+    mock.assert_not_requested("/foo");
+}
+
+#[tokio::test]
+async fn expect_full_request() {
+    let _ = env_logger::try_init();
+
+    let mock = Server::bind().await;
+    let url = format!("http://localhost:{}/foo?bar=baz", mock.port());
+    let mut request = mock.anticipate("/foo").await;
+
+    futures::future::join(
+        async {
+            reqwest::Client::new()
+                .get(url)
+                .header("x-custom", "walkers")
+                .send()
+                .await
+                .unwrap();
+        },
+        async {
+            let recorded = request.expect_full().await;
+            assert_eq!(hyper::Method::GET, recorded.method);
+            assert_eq!("/foo", recorded.path);
+            assert_eq!(Some("bar=baz"), recorded.query.as_deref());
+            assert_eq!("walkers", recorded.headers["x-custom"]);
+            assert!(recorded.body.is_empty());
+            request.respond(Bytes::from_static(b"hello")).await;
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn default_response_for_unanticipated_request() {
+    let _ = env_logger::try_init();
+
+    let mock = Server::bind().await;
+    mock.default_response(|path| (path == "/favicon.ico").then(|| Bytes::from_static(b"icon")));
+
+    let url = format!("http://localhost:{}/favicon.ico", mock.port());
+    let response = reqwest::get(url).await.unwrap();
+    assert_eq!(200, response.status());
+    assert_eq!(&response.bytes().await.unwrap()[..], b"icon");
+}
+
+#[tokio::test]
+#[should_panic(expected = "there are unexpected requests")]
+async fn default_response_can_leave_request_unexpected() {
+    let _ = env_logger::try_init();
+
+    let mock = Server::bind().await;
+    mock.default_response(|path| (path == "/favicon.ico").then(|| Bytes::from_static(b"icon")));
+
+    let url = format!("http://localhost:{}/foo", mock.port());
+    let response = reqwest::get(url).await.unwrap();
+    assert_eq!(418, response.status());
+}
+
+#[tokio::test]
+async fn port_is_released_after_shutdown() {
+    let _ = env_logger::try_init();
+
+    let mock = Server::bind().await;
+    let port = mock.port();
+    assert!(std::net::TcpStream::connect(("127.0.0.1", port)).is_ok());
+
+    mock.shutdown().await;
+    assert!(std::net::TcpStream::connect(("127.0.0.1", port)).is_err());
+}
+
+#[tokio::test]
+async fn streamed_response_is_reassembled() {
+    let _ = env_logger::try_init();
+
+    let mock = Server::bind().await;
+    let url = format!("http://localhost:{}/foo", mock.port());
+
+    mock.anticipate("/foo")
+        .await
+        .respond_stream(
+            ["hel", "lo ", "world"].map(|chunk| Bytes::from_static(chunk.as_bytes())),
+            Duration::from_millis(100),
+        )
+        .await;
+
+    let response = reqwest::get(url).await.unwrap();
+    assert_eq!(&response.bytes().await.unwrap()[..], b"hello world");
+}
@@ -24,13 +24,20 @@ pub(crate) enum Center {
     /// Map is currently moving due to inertia, and will slow down and stop after a short while.
     Inertia {
         position: AdjustedPosition,
-        direction: Vec2,
+
+        /// Speed at the time of release, in pixels per second.
+        velocity: Vec2,
         amount: f32,
     },
 }
 
 impl Center {
-    pub(crate) fn recalculate_drag(&mut self, response: &Response, my_position: Position) -> bool {
+    pub(crate) fn recalculate_drag(
+        &mut self,
+        response: &Response,
+        my_position: Position,
+        inertia: bool,
+    ) -> bool {
         if response.dragged_by(egui::PointerButton::Primary) {
             *self = Center::Moving {
                 position: self
@@ -40,24 +47,36 @@ impl Center {
             };
             true
         } else if response.drag_stopped() {
-            if let Center::Moving {
-                position,
-                direction,
-            } = &self
-            {
-                *self = Center::Inertia {
-                    position: position.clone(),
-                    direction: *direction,
-                    amount: 1.0,
-                };
-            }
+            self.drag_stopped(inertia, response.ctx.input(|input| input.stable_dt));
             true
         } else {
             false
         }
     }
 
-    pub(crate) fn update_movement(&mut self) -> bool {
+    /// User released the map. It either keeps moving due to inertia, or stops right away. `dt` is
+    /// the duration of the last frame, during which the map moved by the drag's `direction`.
+    pub(crate) fn drag_stopped(&mut self, inertia: bool, dt: f32) {
+        if let Center::Moving {
+            position,
+            direction,
+        } = &self
+        {
+            *self = if inertia {
+                Center::Inertia {
+                    position: position.clone(),
+                    velocity: *direction / dt.max(f32::EPSILON),
+                    amount: 1.0,
+                }
+            } else {
+                Center::Exact(position.clone())
+            };
+        }
+    }
+
+    /// Advance the movement by `dt` seconds. Inertia decays exponentially with time constant of
+    /// `tau` seconds, and stops once it falls below `stop_threshold`.
+    pub(crate) fn update_movement(&mut self, dt: f32, tau: f32, stop_threshold: f32) -> bool {
         match &self {
             Center::Moving {
                 position,
@@ -74,19 +93,22 @@ impl Center {
             }
             Center::Inertia {
                 position,
-                direction,
+                velocity,
                 amount,
             } => {
-                *self = if amount <= &mut 0.0 {
+                *self = if tau <= 0.0 || *amount < stop_threshold {
                     Center::Exact(position.to_owned())
                 } else {
-                    let delta = *direction * *amount;
+                    // Integrate the exponentially decaying speed over the frame, so the distance
+                    // does not depend on the frame rate.
+                    let decay = (-dt / tau).exp();
+                    let delta = *velocity * *amount * tau * (1. - decay);
                     let offset = position.offset + Pixels::new(delta.x as f64, delta.y as f64);
 
                     Center::Inertia {
                         position: AdjustedPosition::new(position.position, offset),
-                        direction: *direction,
-                        amount: *amount - 0.03,
+                        velocity: *velocity,
+                        amount: *amount * decay,
                     }
                 };
                 true
@@ -128,11 +150,11 @@ impl Center {
             },
             Center::Inertia {
                 position,
-                direction,
+                velocity,
                 amount,
             } => Center::Inertia {
                 position: position.zero_offset(zoom),
-                direction,
+                velocity,
                 amount,
             },
         }
@@ -152,13 +174,74 @@ impl Center {
             },
             Center::Inertia {
                 position,
-                direction,
+                velocity,
                 amount,
             } => Center::Inertia {
                 position: position.shift(offset),
-                direction,
+                velocity,
                 amount,
             },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lon_lat;
+
+    fn inertia() -> Center {
+        Center::Inertia {
+            position: AdjustedPosition::from(lon_lat(17.03664, 51.09916)),
+            velocity: Vec2::new(600., 0.),
+            amount: 1.0,
+        }
+    }
+
+    /// How far the map glides, when updated with frames of `dt` seconds.
+    fn glide_distance(dt: f32) -> f64 {
+        let mut center = inertia();
+        while center.update_movement(dt, 0.3, 0.1) {}
+        center.adjusted_position().unwrap().offset.x()
+    }
+
+    #[test]
+    fn inertia_slows_down_and_stops() {
+        let mut center = inertia();
+        let mut frames = 0;
+
+        while center.update_movement(1. / 60., 0.2, 0.1) {
+            frames += 1;
+            assert!(frames < 100, "inertia never stopped");
+        }
+
+        assert!(matches!(center, Center::Exact(_)));
+
+        // Time needed to decay from 1.0 to 0.1 is tau * ln(10), which is about 28 frames.
+        assert!((27..=30).contains(&frames), "{frames}");
+    }
+
+    #[test]
+    fn longer_tau_glides_longer() {
+        let count_frames = |tau| {
+            let mut center = inertia();
+            let mut frames = 0;
+            while center.update_movement(1. / 60., tau, 0.1) {
+                frames += 1;
+            }
+            frames
+        };
+
+        assert!(count_frames(0.5) > count_frames(0.2));
+    }
+
+    #[test]
+    fn glide_distance_does_not_depend_on_frame_rate() {
+        let at_60_fps = glide_distance(1. / 60.);
+
+        // Speed of 600px/s decays to 10% in tau * ln(10), covering 90% of 600px/s * tau.
+        approx::assert_relative_eq!(162., at_60_fps, max_relative = 0.05);
+        approx::assert_relative_eq!(at_60_fps, glide_distance(1. / 30.), max_relative = 0.05);
+        approx::assert_relative_eq!(at_60_fps, glide_distance(1. / 144.), max_relative = 0.05);
+    }
+}
@@ -1,18 +1,28 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
-use egui::Context;
+use egui::{ColorImage, Context, TextureOptions};
 use futures::{
     future::{select, select_all, Either},
     SinkExt, StreamExt,
 };
 use image::ImageError;
 use reqwest::header::USER_AGENT;
-use reqwest_middleware::ClientWithMiddleware;
+use reqwest_middleware::{ClientWithMiddleware, Middleware};
 
-use crate::{io::http_client, mercator::TileId, sources::TileSource, tiles::Texture};
+use crate::{
+    io::http_client,
+    mercator::TileId,
+    sources::TileSource,
+    tiles::{decode, is_transparent, Texture},
+};
 
 pub use reqwest::header::HeaderValue;
 
+/// Function turning the downloaded bytes into an image. See [`HttpOptions::decoder`].
+pub type Decoder = Arc<
+    dyn Fn(&[u8]) -> Result<ColorImage, Box<dyn std::error::Error + Send + Sync>> + Send + Sync,
+>;
+
 /// Controls how [`crate::HttpTiles`] use the HTTP protocol, such as caching.
 pub struct HttpOptions {
     /// Path to the directory to store the HTTP cache.
@@ -30,6 +40,45 @@ pub struct HttpOptions {
     /// This should be set only on native targets. The browser sets its own user agent on wasm
     /// targets, and trying to set a different one may upset some servers (e.g. MapBox)
     pub user_agent: Option<HeaderValue>,
+
+    /// Treat fully transparent tiles as missing, so they can be substituted by the tiles of lower
+    /// zoom levels. Some servers respond with such images, if they have no data for the tile.
+    pub skip_transparent_tiles: bool,
+
+    /// Keep the decoded pixels of the tiles in memory, so they can be read with
+    /// [`crate::HttpTiles::sample`]. This roughly doubles the memory used by the tiles.
+    pub keep_pixels: bool,
+
+    /// Limit of the memory used by the textures of downloaded tiles, in bytes. Least recently used
+    /// tiles are evicted once it is exceeded. Useful with large (e.g. 512px or HiDPI) tiles, as the
+    /// number of tiles kept is limited anyway.
+    pub max_texture_bytes: Option<usize>,
+
+    /// Additional middleware, such as authentication or metrics, to be used by the HTTP client.
+    /// They are run in the given order, after the cache, so they see only the requests which
+    /// actually go to the tile server.
+    pub middleware: Vec<Arc<dyn Middleware>>,
+
+    /// Maximum time of the whole request, from connecting until the tile is fully received. Tiles
+    /// which take longer are treated as failed, so a hanging server does not occupy a download
+    /// slot forever.
+    ///
+    /// This option is ignored in WASM, where timeouts are controlled by the browser.
+    pub request_timeout: Option<Duration>,
+
+    /// Maximum time of establishing the connection to the tile server.
+    ///
+    /// This option is ignored in WASM, where timeouts are controlled by the browser.
+    pub connect_timeout: Option<Duration>,
+
+    /// Custom decoder of the downloaded tiles, for formats which are not PNG or JPEG. If not set,
+    /// the tiles are decoded with the `image` crate.
+    pub decoder: Option<Decoder>,
+
+    /// How the textures of the tiles are sampled. Use [`TextureOptions::NEAREST`] for tiles
+    /// whose exact pixel values matter, such as categorical data, which linear filtering would
+    /// blur.
+    pub texture_options: TextureOptions,
 }
 
 impl Default for HttpOptions {
@@ -47,6 +96,14 @@ impl Default for HttpOptions {
         Self {
             cache: None,
             user_agent,
+            skip_transparent_tiles: false,
+            keep_pixels: false,
+            max_texture_bytes: None,
+            middleware: Vec::new(),
+            request_timeout: None,
+            connect_timeout: None,
+            decoder: None,
+            texture_options: TextureOptions::default(),
         }
     }
 }
@@ -62,6 +119,9 @@ enum Error {
     #[error(transparent)]
     Image(ImageError),
 
+    #[error("Custom decoder failed: {0}")]
+    Decoder(Box<dyn std::error::Error + Send + Sync>),
+
     #[error("Tile request channel from the main thread was broken.")]
     RequestChannelBroken,
 
@@ -70,6 +130,9 @@ enum Error {
 
     #[error("Tile channel to the main thread was full.")]
     TileChannelFull,
+
+    #[error("Tile is fully transparent.")]
+    TransparentTile,
 }
 
 impl From<futures::channel::mpsc::SendError> for Error {
@@ -82,23 +145,41 @@ impl From<futures::channel::mpsc::SendError> for Error {
     }
 }
 
+/// Tile to be downloaded. The `generation` is passed back with the downloaded tile, so
+/// [`crate::HttpTiles`] can recognize downloads which were invalidated in the meantime.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TileRequest {
+    pub tile_id: TileId,
+    pub generation: u64,
+}
+
 struct Download {
-    tile_id: TileId,
+    request: TileRequest,
     result: Result<Texture, Error>,
 }
 
+/// Parts of [`HttpOptions`] which control what happens with the tile after it is downloaded.
+#[derive(Clone, Default)]
+struct DecodeOptions {
+    skip_transparent_tiles: bool,
+    keep_pixels: bool,
+    decoder: Option<Decoder>,
+    texture_options: TextureOptions,
+}
+
 /// Download and decode the tile.
 async fn download_and_decode(
     client: &ClientWithMiddleware,
-    tile_id: TileId,
+    request: TileRequest,
     url: String,
     user_agent: Option<&HeaderValue>,
+    decode_options: &DecodeOptions,
     egui_ctx: &Context,
 ) -> Download {
     log::trace!("Downloading '{}'.", url);
     Download {
-        tile_id,
-        result: download_and_decode_impl(client, url, user_agent, egui_ctx).await,
+        request,
+        result: download_and_decode_impl(client, url, user_agent, decode_options, egui_ctx).await,
     }
 }
 
@@ -106,6 +187,7 @@ async fn download_and_decode_impl(
     client: &ClientWithMiddleware,
     url: String,
     user_agent: Option<&HeaderValue>,
+    decode_options: &DecodeOptions,
     egui_ctx: &Context,
 ) -> Result<Texture, Error> {
     let mut image_request = client.get(&url);
@@ -125,22 +207,48 @@ async fn download_and_decode_impl(
         .await
         .map_err(Error::Http)?;
 
-    Texture::new(&image, egui_ctx).map_err(Error::Image)
+    let image = match &decode_options.decoder {
+        Some(decoder) => decoder(&image).map_err(Error::Decoder)?,
+        None => decode(&image).map_err(Error::Image)?,
+    };
+
+    if decode_options.skip_transparent_tiles && is_transparent(&image) {
+        return Err(Error::TransparentTile);
+    }
+
+    let options = decode_options.texture_options;
+    if decode_options.keep_pixels {
+        Ok(Texture::from_color_image_keeping_pixels(
+            image, egui_ctx, options,
+        ))
+    } else {
+        Ok(Texture::from_color_image_with_options(
+            image, egui_ctx, options,
+        ))
+    }
 }
 
 async fn download_complete(
-    mut tile_tx: futures::channel::mpsc::Sender<(TileId, Texture)>,
+    mut tile_tx: futures::channel::mpsc::Sender<(TileRequest, Texture)>,
     egui_ctx: Context,
     download: Download,
 ) -> Result<(), Error> {
     match download.result {
         Ok(tile) => {
             tile_tx
-                .send((download.tile_id, tile))
+                .send((download.request, tile))
                 .await
                 .map_err(Error::from)?;
             egui_ctx.request_repaint();
         }
+        Err(Error::TransparentTile) => {
+            // Expected, since the user opted in to skipping such tiles.
+            log::debug!(
+                "Skipping {:?}: {}",
+                download.request.tile_id,
+                Error::TransparentTile
+            );
+        }
         Err(e) => {
             // It would probably be more consistent to push it to the caller, but it's not that
             // important right now.
@@ -151,6 +259,16 @@ async fn download_complete(
     Ok(())
 }
 
+/// URL of the tile, in the resolution matching the display.
+fn tile_url(source: &impl TileSource, tile_id: TileId, pixels_per_point: f32) -> String {
+    if pixels_per_point > 1. {
+        if let Some(url) = source.hidpi_tile_url(tile_id) {
+            return url;
+        }
+    }
+    source.tile_url(tile_id)
+}
+
 /// Maximum number of parallel downloads. Following modern browsers' behavior.
 /// https://stackoverflow.com/questions/985431/max-parallel-http-connections-in-a-browser
 pub(crate) const MAX_PARALLEL_DOWNLOADS: usize = 6;
@@ -158,14 +276,20 @@ pub(crate) const MAX_PARALLEL_DOWNLOADS: usize = 6;
 async fn download_continuously_impl<S>(
     source: S,
     http_options: HttpOptions,
-    mut request_rx: futures::channel::mpsc::Receiver<TileId>,
-    tile_tx: futures::channel::mpsc::Sender<(TileId, Texture)>,
+    mut request_rx: futures::channel::mpsc::Receiver<TileRequest>,
+    tile_tx: futures::channel::mpsc::Sender<(TileRequest, Texture)>,
     egui_ctx: Context,
 ) -> Result<(), Error>
 where
     S: TileSource + Send + 'static,
 {
     let user_agent = http_options.user_agent.clone();
+    let decode_options = DecodeOptions {
+        skip_transparent_tiles: http_options.skip_transparent_tiles,
+        keep_pixels: http_options.keep_pixels,
+        decoder: http_options.decoder.clone(),
+        texture_options: http_options.texture_options,
+    };
 
     // Keep outside the loop to reuse it as much as possible.
     let client = http_client(http_options);
@@ -174,10 +298,16 @@ where
     loop {
         if downloads.is_empty() {
             // Only new downloads might be requested.
-            let tile_id = request_rx.next().await.ok_or(Error::RequestChannelBroken)?;
-            let url = source.tile_url(tile_id);
-            let download =
-                download_and_decode(&client, tile_id, url, user_agent.as_ref(), &egui_ctx);
+            let request = request_rx.next().await.ok_or(Error::RequestChannelBroken)?;
+            let url = tile_url(&source, request.tile_id, egui_ctx.pixels_per_point());
+            let download = download_and_decode(
+                &client,
+                request,
+                url,
+                user_agent.as_ref(),
+                &decode_options,
+                &egui_ctx,
+            );
             downloads.push(Box::pin(download));
         } else if downloads.len() < MAX_PARALLEL_DOWNLOADS {
             // New downloads might be requested or ongoing downloads might be completed.
@@ -185,10 +315,16 @@ where
             match select(request_rx.next(), download).await {
                 // New download was requested.
                 Either::Left((request, remaining_downloads)) => {
-                    let tile_id = request.ok_or(Error::RequestChannelBroken)?;
-                    let url = source.tile_url(tile_id);
-                    let download =
-                        download_and_decode(&client, tile_id, url, user_agent.as_ref(), &egui_ctx);
+                    let request = request.ok_or(Error::RequestChannelBroken)?;
+                    let url = tile_url(&source, request.tile_id, egui_ctx.pixels_per_point());
+                    let download = download_and_decode(
+                        &client,
+                        request,
+                        url,
+                        user_agent.as_ref(),
+                        &decode_options,
+                        &egui_ctx,
+                    );
                     downloads = remaining_downloads.into_inner();
                     downloads.push(Box::pin(download));
                 }
@@ -211,8 +347,8 @@ where
 pub(crate) async fn download_continuously<S>(
     source: S,
     http_options: HttpOptions,
-    request_rx: futures::channel::mpsc::Receiver<TileId>,
-    tile_tx: futures::channel::mpsc::Sender<(TileId, Texture)>,
+    request_rx: futures::channel::mpsc::Receiver<TileRequest>,
+    tile_tx: futures::channel::mpsc::Sender<(TileRequest, Texture)>,
     egui_ctx: Context,
 ) where
     S: TileSource + Send + 'static,
@@ -226,3 +362,66 @@ pub(crate) async fn download_continuously<S>(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::{Mapbox, OpenStreetMap};
+
+    static TILE_ID: TileId = TileId {
+        x: 1,
+        y: 2,
+        zoom: 3,
+    };
+
+    #[test]
+    fn hidpi_variant_is_used_on_hidpi_displays() {
+        let source = Mapbox {
+            access_token: "token".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            "https://api.mapbox.com/styles/v1/mapbox/streets-v12/tiles/512/3/1/2?access_token=token",
+            tile_url(&source, TILE_ID, 1.)
+        );
+        assert_eq!(
+            "https://api.mapbox.com/styles/v1/mapbox/streets-v12/tiles/512/3/1/2@2x?access_token=token",
+            tile_url(&source, TILE_ID, 2.)
+        );
+    }
+
+    #[test]
+    fn regular_variant_is_used_if_there_is_no_hidpi_one() {
+        assert_eq!(
+            "https://tile.openstreetmap.org/3/1/2.png",
+            tile_url(&OpenStreetMap, TILE_ID, 2.)
+        );
+    }
+
+    #[tokio::test]
+    async fn request_exceeding_timeout_fails() {
+        let _ = env_logger::try_init();
+
+        let server = hypermocker::Server::bind().await;
+        let url = format!("http://localhost:{}/3/1/2.png", server.port());
+        let mut anticipated = server.anticipate("/3/1/2.png").await;
+
+        let client = http_client(HttpOptions {
+            request_timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        });
+
+        let ctx = Context::default();
+        let decode_options = DecodeOptions::default();
+        let download = download_and_decode_impl(&client, url, None, &decode_options, &ctx);
+
+        // Server never responds, so without the timeout, this would hang.
+        let (result, _) = futures::join!(
+            tokio::time::timeout(Duration::from_secs(5), download),
+            anticipated.expect()
+        );
+
+        assert!(matches!(result, Ok(Err(Error::HttpMiddleware(_)))));
+    }
+}
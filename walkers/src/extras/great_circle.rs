@@ -0,0 +1,137 @@
+use crate::{lon_lat, Position};
+
+/// Positions along the great circle, i.e. the shortest route on the globe, going from `from` to
+/// `to`, split into `segments` equal parts, like flight routes on airline maps.
+///
+/// The route is returned as a list of lines, because it is split where it crosses the
+/// antimeridian, so no line jumps across the whole map. The lines can be drawn with e.g.
+/// [`crate::Projector::project`] and [`egui::Shape::line`].
+///
+/// There is no single great circle between two antipodal positions, so nothing is returned for
+/// them.
+pub fn great_circle(from: Position, to: Position, segments: usize) -> Vec<Vec<Position>> {
+    let (a, b) = (unit_vector(from), unit_vector(to));
+    let cross = [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ];
+    let sin_distance = cross.iter().map(|c| c * c).sum::<f64>().sqrt();
+    let distance = sin_distance.atan2(a.iter().zip(b).map(|(a, b)| a * b).sum());
+
+    if sin_distance < 1e-12 && distance > 1. {
+        return Vec::new();
+    }
+
+    let segments = segments.max(1);
+    let points = (0..=segments).map(|i| {
+        let fraction = i as f64 / segments as f64;
+        if sin_distance < 1e-12 {
+            // Both positions are the same.
+            return from;
+        }
+
+        let from_weight = ((1. - fraction) * distance).sin() / sin_distance;
+        let to_weight = (fraction * distance).sin() / sin_distance;
+        let [x, y, z] = [0, 1, 2].map(|i| from_weight * a[i] + to_weight * b[i]);
+        lon_lat(y.atan2(x).to_degrees(), z.atan2(x.hypot(y)).to_degrees())
+    });
+
+    split_at_antimeridian(points)
+}
+
+/// Position as a vector pointing from the center of a unit sphere.
+fn unit_vector(position: Position) -> [f64; 3] {
+    let (lon, lat) = (position.x().to_radians(), position.y().to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+/// Start a new line whenever two consecutive positions are on the opposite sides of the
+/// antimeridian. Both lines are extended to meet it.
+fn split_at_antimeridian(positions: impl Iterator<Item = Position>) -> Vec<Vec<Position>> {
+    let mut lines: Vec<Vec<Position>> = vec![Vec::new()];
+
+    for position in positions {
+        let line = lines.last_mut().expect("there is always at least one line");
+
+        if let Some(previous) = line.last().copied() {
+            if (position.x() - previous.x()).abs() > 180. {
+                let seam = 180f64.copysign(previous.x());
+                let unwrapped = position.x() + 2. * seam;
+                let fraction = (seam - previous.x()) / (unwrapped - previous.x());
+                let lat = previous.y() + fraction * (position.y() - previous.y());
+
+                line.push(lon_lat(seam, lat));
+                lines.push(vec![lon_lat(-seam, lat)]);
+            }
+        }
+
+        lines
+            .last_mut()
+            .expect("there is always at least one line")
+            .push(position);
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_position_eq(expected: Position, actual: Position) {
+        approx::assert_abs_diff_eq!(expected.x(), actual.x(), epsilon = 1e-9);
+        approx::assert_abs_diff_eq!(expected.y(), actual.y(), epsilon = 1e-9);
+    }
+
+    #[test]
+    fn route_along_the_equator() {
+        let lines = great_circle(lon_lat(0., 0.), lon_lat(90., 0.), 2);
+
+        assert_eq!(1, lines.len());
+        assert_eq!(3, lines[0].len());
+        assert_position_eq(lon_lat(0., 0.), lines[0][0]);
+        assert_position_eq(lon_lat(45., 0.), lines[0][1]);
+        assert_position_eq(lon_lat(90., 0.), lines[0][2]);
+    }
+
+    #[test]
+    fn route_bends_towards_the_pole() {
+        let (wroclaw, new_york) = (lon_lat(17.03664, 51.09916), lon_lat(-74.006, 40.7128));
+        let lines = great_circle(wroclaw, new_york, 32);
+
+        assert_eq!(1, lines.len());
+        assert_position_eq(wroclaw, lines[0][0]);
+        assert_position_eq(new_york, *lines[0].last().unwrap());
+
+        // Great circles between two northern positions go further north than both of them.
+        assert!(lines[0].iter().any(|position| position.y() > 55.));
+    }
+
+    #[test]
+    fn transpacific_route_is_split_at_the_antimeridian() {
+        let (tokyo, san_francisco) = (lon_lat(139.78, 35.55), lon_lat(-122.38, 37.62));
+        let lines = great_circle(tokyo, san_francisco, 64);
+
+        assert_eq!(2, lines.len());
+        assert_position_eq(tokyo, lines[0][0]);
+        assert_position_eq(san_francisco, *lines[1].last().unwrap());
+
+        // Both lines meet at the antimeridian.
+        let (west, east) = (lines[0].last().unwrap(), lines[1][0]);
+        assert_eq!(180., west.x());
+        assert_eq!(-180., east.x());
+        assert_eq!(west.y(), east.y());
+
+        for line in &lines {
+            for pair in line.windows(2) {
+                assert!((pair[1].x() - pair[0].x()).abs() < 180.);
+            }
+        }
+    }
+
+    #[test]
+    fn no_route_between_antipodes() {
+        assert!(great_circle(lon_lat(0., 0.), lon_lat(180., 0.), 10).is_empty());
+    }
+}
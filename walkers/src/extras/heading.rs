@@ -0,0 +1,107 @@
+use egui::{emath::Rot2, vec2, Color32, Pos2, Response, Shape, Stroke, Ui};
+
+use crate::{Plugin, Position, Projector};
+
+/// [`Plugin`] which draws a triangle pointing in the direction a moving object, such as a vehicle,
+/// is heading.
+pub struct HeadingMarker {
+    position: Position,
+    heading: f32,
+    size: f32,
+    fill: Color32,
+    stroke: Stroke,
+}
+
+impl HeadingMarker {
+    /// Marker at the `position`, with `heading` given in degrees clockwise from the north.
+    pub fn new(position: Position, heading: f32) -> Self {
+        Self {
+            position,
+            heading,
+            size: 12.,
+            fill: Color32::from_rgb(0x33, 0x88, 0xff),
+            stroke: Stroke::new(2.0_f32, Color32::WHITE),
+        }
+    }
+
+    /// Distance, in pixels, between the marker's center and its tip.
+    pub fn size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Colors of the triangle and its outline.
+    pub fn colors(mut self, fill: Color32, stroke: Stroke) -> Self {
+        self.fill = fill;
+        self.stroke = stroke;
+        self
+    }
+}
+
+/// Vertices of the triangle centered at `center`, with its tip pointing at the `heading`. Since the
+/// Web Mercator is conformal, the screen's up is the north, regardless of the latitude.
+fn triangle(center: Pos2, heading: f32, size: f32) -> [Pos2; 3] {
+    let rotation = Rot2::from_angle(heading.to_radians());
+    [
+        vec2(0., -size),
+        vec2(0.6 * size, 0.6 * size),
+        vec2(-0.6 * size, 0.6 * size),
+    ]
+    .map(|vertex| center + rotation * vertex)
+}
+
+impl Plugin for HeadingMarker {
+    fn run(self: Box<Self>, ui: &mut Ui, _response: &Response, projector: &Projector) {
+        let center = projector.project(self.position).to_pos2();
+        ui.painter().add(Shape::convex_polygon(
+            triangle(center, self.heading, self.size).to_vec(),
+            self.fill,
+            self.stroke,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::pos2;
+
+    fn assert_vertices_eq(expected: [Pos2; 3], actual: [Pos2; 3]) {
+        for (expected, actual) in expected.iter().zip(actual) {
+            assert!(
+                expected.distance(actual) < 0.001,
+                "{expected:?} != {actual:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn triangle_points_at_the_heading() {
+        let center = pos2(100., 100.);
+
+        // North is up.
+        assert_vertices_eq(
+            [pos2(100., 90.), pos2(106., 106.), pos2(94., 106.)],
+            triangle(center, 0., 10.),
+        );
+
+        // East is right.
+        assert_vertices_eq(
+            [pos2(110., 100.), pos2(94., 106.), pos2(94., 94.)],
+            triangle(center, 90., 10.),
+        );
+
+        // South is down.
+        assert_vertices_eq(
+            [pos2(100., 110.), pos2(94., 94.), pos2(106., 94.)],
+            triangle(center, 180., 10.),
+        );
+
+        // West is left, also when given as a negative angle.
+        assert_vertices_eq(triangle(center, 270., 10.), triangle(center, -90., 10.));
+        assert_vertices_eq(
+            [pos2(90., 100.), pos2(106., 94.), pos2(106., 106.)],
+            triangle(center, 270., 10.),
+        );
+    }
+}
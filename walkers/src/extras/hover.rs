@@ -0,0 +1,99 @@
+use egui::{Id, Pos2, Response, Ui};
+
+use crate::{extras::Place, Plugin, Projector};
+
+/// [`Plugin`] which shows a tooltip for the place nearest to the mouse pointer. Only places within
+/// the [`HoverInfo::threshold`] are considered, and only one tooltip is shown at a time.
+///
+/// It does not draw the places themselves, so it is usually accompanied by
+/// [`crate::extras::Places`].
+pub struct HoverInfo<T: Place, F: Fn(&mut Ui, &T)> {
+    places: Vec<T>,
+    tooltip: F,
+    threshold: f32,
+}
+
+impl<T: Place, F: Fn(&mut Ui, &T)> HoverInfo<T, F> {
+    /// Show the `tooltip` for one of the `places`.
+    pub fn new(places: Vec<T>, tooltip: F) -> Self {
+        Self {
+            places,
+            tooltip,
+            threshold: 20.,
+        }
+    }
+
+    /// Maximum distance, in pixels, between the place and the mouse pointer.
+    pub fn threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Place nearest to the `pointer`, if it is within the threshold.
+    fn nearest(&self, projector: &Projector, pointer: Pos2) -> Option<&T> {
+        self.places
+            .iter()
+            .map(|place| (place, projector.screen_distance(place.position(), pointer)))
+            .filter(|(_, distance)| *distance <= self.threshold)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(place, _)| place)
+    }
+}
+
+impl<T: Place, F: Fn(&mut Ui, &T)> Plugin for HoverInfo<T, F> {
+    fn run(self: Box<Self>, ui: &mut Ui, response: &Response, projector: &Projector) {
+        let Some(pointer) = response.hover_pos() else {
+            return;
+        };
+
+        if let Some(place) = self.nearest(projector, pointer) {
+            egui::show_tooltip_at_pointer(
+                ui.ctx(),
+                ui.layer_id(),
+                Id::new("walkers_hover_info"),
+                |ui| (self.tooltip)(ui, place),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lon_lat, MapMemory, Position};
+    use egui::{pos2, vec2, Rect};
+
+    const SCREEN: Rect = Rect::from_min_max(pos2(0., 0.), pos2(800., 600.));
+
+    struct Named(&'static str, Position);
+
+    impl Place for Named {
+        fn position(&self) -> Position {
+            self.1
+        }
+
+        fn draw(&self, _ui: &Ui, _projector: &Projector) {}
+    }
+
+    #[test]
+    fn nearest_place_within_threshold() {
+        let center = lon_lat(17.03664, 51.09916);
+        let projector = Projector::new(SCREEN, &MapMemory::default(), center);
+
+        // Places 5, 15 and 100 pixels to the right of the center.
+        let places = [5., 15., 100.]
+            .into_iter()
+            .zip(["near", "further", "far"])
+            .map(|(x, name)| Named(name, projector.unproject(vec2(x, 0.))))
+            .collect();
+
+        let hover_info = HoverInfo::new(places, |_: &mut Ui, _: &Named| {});
+        let nearest = |pointer: Pos2| hover_info.nearest(&projector, pointer).map(|place| place.0);
+
+        assert_eq!(Some("near"), nearest(SCREEN.center()));
+        assert_eq!(Some("further"), nearest(SCREEN.center() + vec2(12., 0.)));
+        assert_eq!(Some("far"), nearest(SCREEN.center() + vec2(90., 0.)));
+        assert_eq!(None, nearest(SCREEN.center() + vec2(50., 0.)));
+        assert_eq!(None, nearest(SCREEN.center() + vec2(5., 30.)));
+    }
+}
@@ -0,0 +1,106 @@
+use egui::{vec2, Button, Rect, Response, RichText, Ui};
+
+use crate::{InteractivePlugin, MapMemory, Projector};
+
+/// [`InteractivePlugin`] which draws a button bringing the map back to following `my_position`.
+///
+/// ```
+/// # use walkers::{Map, MapMemory, Position, extras::LocateControl};
+/// fn update(ui: &mut egui::Ui, map_memory: &mut MapMemory, my_position: Position) {
+///     ui.add(Map::new(None, map_memory, my_position).with_interactive_plugin(LocateControl));
+/// }
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LocateControl;
+
+/// Where the button is placed within the map's `rect`.
+fn button_rect(rect: Rect) -> Rect {
+    let size = vec2(30., 30.);
+    Rect::from_min_size(rect.right_bottom() - size - vec2(10., 10.), size)
+}
+
+impl InteractivePlugin for LocateControl {
+    fn run(
+        self: Box<Self>,
+        ui: &mut Ui,
+        _response: &Response,
+        _projector: &Projector,
+        memory: &mut MapMemory,
+    ) {
+        if ui
+            .put(
+                button_rect(ui.max_rect()),
+                Button::new(RichText::new("◎").heading()),
+            )
+            .on_hover_text("follow my position")
+            .clicked()
+        {
+            memory.follow_my_position();
+        }
+    }
+
+    fn gesture_exclusion(&self, rect: Rect, _projector: &Projector) -> Option<Rect> {
+        Some(button_rect(rect))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lon_lat, Map};
+    use egui::{pos2, Context, Event, PointerButton, RawInput};
+
+    fn show_map(ctx: &Context, memory: &mut MapMemory, events: Vec<Event>) {
+        let input = RawInput {
+            screen_rect: Some(Rect::from_min_size(pos2(0., 0.), vec2(800., 600.))),
+            events,
+            ..Default::default()
+        };
+
+        let _ = ctx.run(input, |ctx| {
+            egui::CentralPanel::default()
+                .frame(egui::Frame::none())
+                .show(ctx, |ui| {
+                    ui.add(
+                        Map::new(None, memory, lon_lat(17.03664, 51.09916))
+                            .with_interactive_plugin(LocateControl),
+                    );
+                });
+        });
+    }
+
+    #[test]
+    fn clicking_the_button_makes_the_map_follow_my_position() {
+        let ctx = Context::default();
+        let mut memory = MapMemory::default();
+
+        memory.center_at(lon_lat(21.00027, 52.26470));
+        assert!(memory.detached().is_some());
+
+        let button = pos2(800. - 25., 600. - 25.);
+        show_map(&ctx, &mut memory, vec![Event::PointerMoved(button)]);
+        show_map(
+            &ctx,
+            &mut memory,
+            vec![Event::PointerButton {
+                pos: button,
+                button: PointerButton::Primary,
+                pressed: true,
+                modifiers: Default::default(),
+            }],
+        );
+        assert!(memory.detached().is_some());
+
+        show_map(
+            &ctx,
+            &mut memory,
+            vec![Event::PointerButton {
+                pos: button,
+                button: PointerButton::Primary,
+                pressed: false,
+                modifiers: Default::default(),
+            }],
+        );
+        assert!(memory.detached().is_none());
+    }
+}
@@ -1,6 +1,20 @@
 //! Extra functionalities that can be used with the map.
 mod places;
-pub use places::{Place, Places, Style};
+pub use places::{LabeledSymbol, LabeledSymbolStyle, Place, Places};
 mod images;
 pub use crate::tiles::Texture;
 pub use images::{Image, Images};
+mod locate;
+pub use locate::LocateControl;
+mod overlay;
+pub use overlay::ImageOverlay;
+mod heading;
+pub use heading::HeadingMarker;
+mod hover;
+pub use hover::HoverInfo;
+mod readout;
+pub use readout::CoordinateReadout;
+mod tapered;
+pub use tapered::tapered_line;
+mod great_circle;
+pub use great_circle::great_circle;
@@ -0,0 +1,85 @@
+use egui::{Color32, Rect, Response, Ui};
+
+use crate::{tiles::Texture, Plugin, Position, Projector};
+
+/// [`Plugin`] which stretches an image over a geographical area, e.g. a frame of a weather radar.
+/// The image is expected to be in the Web Mercator projection, like the tiles are.
+///
+/// Since plugins are created each frame, animating is a matter of passing a different frame's
+/// [`Texture`] each time.
+pub struct ImageOverlay {
+    texture: Texture,
+    north_west: Position,
+    south_east: Position,
+    opacity: f32,
+}
+
+impl ImageOverlay {
+    /// Create the overlay spanning from the `north_west` to the `south_east` corner.
+    pub fn new(texture: Texture, north_west: Position, south_east: Position) -> Self {
+        Self {
+            texture,
+            north_west,
+            south_east,
+            opacity: 1.0,
+        }
+    }
+
+    /// Set the opacity, from `0.0` (invisible) to `1.0` (opaque), so the tiles below can be seen.
+    pub fn opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity;
+        self
+    }
+
+    /// Where on the screen the overlay is drawn.
+    pub fn screen_rect(&self, projector: &Projector) -> Rect {
+        Rect::from_two_pos(
+            projector.project(self.north_west).to_pos2(),
+            projector.project(self.south_east).to_pos2(),
+        )
+    }
+}
+
+impl Plugin for ImageOverlay {
+    fn run(self: Box<Self>, ui: &mut Ui, _response: &Response, projector: &Projector) {
+        let rect = self.screen_rect(projector);
+
+        if ui.max_rect().intersects(rect) {
+            let mut mesh = self.texture.mesh_with_rect(rect);
+            let tint = Color32::WHITE.gamma_multiply(self.opacity);
+            for vertex in &mut mesh.vertices {
+                vertex.color = tint;
+            }
+            ui.painter().add(mesh);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lon_lat, MapMemory};
+    use egui::{pos2, ColorImage, Context};
+
+    #[test]
+    fn overlay_covering_the_whole_world() {
+        let mut memory = MapMemory::default();
+        memory.set_zoom(0.).unwrap();
+        let screen = Rect::from_min_max(pos2(0., 0.), pos2(800., 600.));
+        let projector = Projector::new(screen, &memory, lon_lat(0., 0.));
+
+        let texture =
+            Texture::from_color_image(ColorImage::new([16, 16], Color32::RED), &Context::default());
+        let overlay = ImageOverlay::new(
+            texture,
+            lon_lat(-180., 85.0511287798066),
+            lon_lat(180., -85.0511287798066),
+        )
+        .opacity(0.5);
+
+        // At zoom 0, the whole world is a single 256px tile.
+        let rect = overlay.screen_rect(&projector);
+        assert!(rect.min.distance(pos2(272., 172.)) < 0.01, "{rect:?}");
+        assert!(rect.max.distance(pos2(528., 428.)) < 0.01, "{rect:?}");
+    }
+}
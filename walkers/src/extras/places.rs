@@ -1,10 +1,66 @@
-use egui::{vec2, Align2, Color32, FontId, Response, Stroke, Ui};
+use egui::{
+    vec2, Align2, Color32, FontId, Painter, Pos2, Rect, Response, Stroke, Ui, Vec2, Visuals,
+};
 
-use crate::{Plugin, Position};
+use crate::{Plugin, Position, Projector};
 
-/// Visual style of the place.
+/// A place to be drawn on the map.
+pub trait Place {
+    /// Geographical position of the place.
+    fn position(&self) -> Position;
+
+    /// Draw the place on the map.
+    fn draw(&self, ui: &Ui, projector: &Projector);
+
+    /// Area of the screen occupied by the place. Places whose bounds do not intersect the
+    /// viewport are not drawn at all.
+    fn screen_bounds(&self, projector: &Projector) -> Rect {
+        Rect::from_center_size(
+            projector.project(self.position()).to_pos2(),
+            Vec2::splat(2. * SYMBOL_RADIUS),
+        )
+    }
+
+    /// Lowest zoom level at which the place is drawn.
+    fn min_zoom(&self) -> f64 {
+        0.
+    }
+
+    /// Highest zoom level at which the place is drawn.
+    fn max_zoom(&self) -> f64 {
+        f64::INFINITY
+    }
+}
+
+/// Allows different kinds of places to be drawn by a single [`Places`] plugin.
+impl<T: Place + ?Sized> Place for Box<T> {
+    fn position(&self) -> Position {
+        (**self).position()
+    }
+
+    fn draw(&self, ui: &Ui, projector: &Projector) {
+        (**self).draw(ui, projector)
+    }
+
+    fn screen_bounds(&self, projector: &Projector) -> Rect {
+        (**self).screen_bounds(projector)
+    }
+
+    fn min_zoom(&self) -> f64 {
+        (**self).min_zoom()
+    }
+
+    fn max_zoom(&self) -> f64 {
+        (**self).max_zoom()
+    }
+}
+
+/// Radius of the circle drawn around [`LabeledSymbol::symbol`].
+const SYMBOL_RADIUS: f32 = 12.;
+
+/// Visual style of the [`LabeledSymbol`].
 #[derive(Clone)]
-pub struct Style {
+pub struct LabeledSymbolStyle {
     pub label_font: FontId,
     pub label_color: Color32,
     pub label_background: Color32,
@@ -12,9 +68,18 @@ pub struct Style {
     pub symbol_color: Color32,
     pub symbol_background: Color32,
     pub symbol_stroke: Stroke,
+
+    /// Offset of the label, relative to the symbol's center.
+    pub label_offset: Vec2,
+
+    /// Line connecting the symbol with its label, if any.
+    pub leader_line: Option<Stroke>,
+
+    /// Round the symbol's screen position to whole physical pixels, so it does not look blurry.
+    pub round_to_pixel: bool,
 }
 
-impl Default for Style {
+impl Default for LabeledSymbolStyle {
     fn default() -> Self {
         Self {
             label_font: FontId::proportional(12.),
@@ -23,13 +88,34 @@ impl Default for Style {
             symbol_font: FontId::proportional(14.),
             symbol_color: Color32::BLACK.gamma_multiply(0.8),
             symbol_background: Color32::WHITE.gamma_multiply(0.8),
-            symbol_stroke: Stroke::new(2., Color32::BLACK.gamma_multiply(0.8)),
+            symbol_stroke: Stroke::new(2.0_f32, Color32::BLACK.gamma_multiply(0.8)),
+            label_offset: vec2(8., 8.),
+            leader_line: None,
+            round_to_pixel: false,
         }
     }
 }
 
-/// A place to be drawn on the map.
-pub struct Place {
+impl LabeledSymbolStyle {
+    /// Style fitting the theme of the `visuals`. The [`Default`] one is meant for the dark theme.
+    pub fn for_visuals(visuals: &Visuals) -> Self {
+        if visuals.dark_mode {
+            Self::default()
+        } else {
+            Self {
+                label_color: Color32::from_gray(50),
+                label_background: Color32::WHITE.gamma_multiply(0.8),
+                symbol_color: Color32::WHITE.gamma_multiply(0.8),
+                symbol_background: Color32::BLACK.gamma_multiply(0.8),
+                symbol_stroke: Stroke::new(2.0_f32, Color32::WHITE.gamma_multiply(0.8)),
+                ..Self::default()
+            }
+        }
+    }
+}
+
+/// A symbol with a label to be drawn on the map.
+pub struct LabeledSymbol {
     /// Geographical position.
     pub position: Position,
 
@@ -41,13 +127,47 @@ pub struct Place {
     pub symbol: char,
 
     /// Visual style of this place.
-    pub style: Style,
+    pub style: LabeledSymbolStyle,
 }
 
-impl Place {
-    fn draw(&self, ui: &Ui, projector: &crate::Projector) {
-        let screen_position = projector.project(self.position);
+impl LabeledSymbol {
+    /// Where the symbol is drawn on the screen.
+    fn screen_position(&self, projector: &Projector, painter: &Painter) -> Pos2 {
+        let screen_position = projector.project(self.position).to_pos2();
+        if self.style.round_to_pixel {
+            painter.round_pos_to_pixels(screen_position)
+        } else {
+            screen_position
+        }
+    }
+
+    /// Background of the label of given size, when the symbol is at `screen_position`.
+    fn label_rect(&self, screen_position: Pos2, label_size: Vec2) -> Rect {
+        Rect::from_min_size(screen_position + self.style.label_offset, label_size).expand(5.)
+    }
+
+    /// Leader line going from the symbol's circle to the nearest point of the label, unless they
+    /// overlap.
+    fn leader_line(&self, screen_position: Pos2, label_rect: Rect) -> Option<[Pos2; 2]> {
+        let end = label_rect.clamp(screen_position);
+        let direction = end - screen_position;
+        (direction.length() > SYMBOL_RADIUS).then(|| {
+            [
+                screen_position + direction.normalized() * SYMBOL_RADIUS,
+                end,
+            ]
+        })
+    }
+}
+
+impl Place for LabeledSymbol {
+    fn position(&self) -> Position {
+        self.position
+    }
+
+    fn draw(&self, ui: &Ui, projector: &Projector) {
         let painter = ui.painter();
+        let screen_position = self.screen_position(projector, painter);
 
         let label = painter.layout_no_wrap(
             self.label.to_owned(),
@@ -55,53 +175,391 @@ impl Place {
             self.style.label_color,
         );
 
-        // Offset of the label, relative to the circle.
-        let offset = vec2(8., 8.);
-
-        painter.rect_filled(
-            label
-                .rect
-                .translate(screen_position)
-                .translate(offset)
-                .expand(5.),
-            10.,
-            self.style.label_background,
-        );
+        let label_rect = self.label_rect(screen_position, label.size());
 
-        painter.galley((screen_position + offset).to_pos2(), label, Color32::BLACK);
+        if let Some(stroke) = self.style.leader_line {
+            if let Some(points) = self.leader_line(screen_position, label_rect) {
+                painter.line_segment(points, stroke);
+            }
+        }
+
+        painter.rect_filled(label_rect, 10., self.style.label_background);
+
+        painter.galley(
+            screen_position + self.style.label_offset,
+            label,
+            Color32::BLACK,
+        );
 
         painter.circle(
-            screen_position.to_pos2(),
-            10.,
+            screen_position,
+            SYMBOL_RADIUS,
             self.style.symbol_background,
             self.style.symbol_stroke,
         );
 
         painter.text(
-            screen_position.to_pos2(),
+            screen_position,
             Align2::CENTER_CENTER,
             self.symbol.to_string(),
             self.style.symbol_font.clone(),
             self.style.symbol_color,
         );
     }
+
+    fn screen_bounds(&self, projector: &Projector) -> Rect {
+        // Rounding to pixels moves the symbol by less than a pixel, which is well within the
+        // generous estimate below.
+        let screen_position = projector.project(self.position).to_pos2();
+
+        // Label is not laid out yet, so its size is estimated generously, assuming that no glyph
+        // is wider than the font size.
+        let font_size = self.style.label_font.size;
+        let lines = self.label.lines().count().max(1) as f32;
+        let longest = self.label.lines().map(|line| line.chars().count()).max();
+        let label_size = vec2(
+            longest.unwrap_or_default() as f32 * font_size,
+            lines * font_size * 1.5,
+        );
+
+        Rect::from_center_size(screen_position, Vec2::splat(2. * SYMBOL_RADIUS))
+            .union(self.label_rect(screen_position, label_size))
+    }
 }
 
-/// [`Plugin`] which draws list of places on the map.
-pub struct Places {
-    places: Vec<Place>,
+/// [`Plugin`] which draws list of places on the map. Places of different types can be mixed by
+/// boxing them, i.e. `Places<Box<dyn Place>>`, which is the default.
+pub struct Places<T: Place = Box<dyn Place>> {
+    places: Vec<T>,
 }
 
-impl Places {
-    pub fn new(places: Vec<Place>) -> Self {
+impl<T: Place> Places<T> {
+    pub fn new(places: Vec<T>) -> Self {
         Self { places }
     }
 }
 
-impl Plugin for Places {
-    fn run(self: Box<Self>, ui: &mut Ui, _response: &Response, projector: &crate::Projector) {
+impl<T: Place> FromIterator<T> for Places<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+impl<T: Place> Plugin for Places<T> {
+    fn run(self: Box<Self>, ui: &mut Ui, _response: &Response, projector: &Projector) {
+        let viewport = ui.max_rect();
+
+        // Places near the antimeridian might be visible on the neighboring copies of the world.
+        let projectors = [-1, 0, 1].map(|offset| projector.world_copy(offset));
+
+        let zoom = projector.zoom();
+
         for place in &self.places {
-            place.draw(ui, projector);
+            if !(place.min_zoom()..=place.max_zoom()).contains(&zoom) {
+                continue;
+            }
+
+            for projector in &projectors {
+                if viewport.intersects(place.screen_bounds(projector)) {
+                    place.draw(ui, projector);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lon_lat, Map, MapMemory};
+    use egui::{pos2, Context, RawInput};
+    use std::cell::Cell;
+
+    /// Place which only counts how many times it was drawn.
+    struct CountingPlace<'a> {
+        position: Position,
+        drawn: &'a Cell<usize>,
+    }
+
+    impl Place for CountingPlace<'_> {
+        fn position(&self) -> Position {
+            self.position
+        }
+
+        fn draw(&self, _ui: &Ui, _projector: &Projector) {
+            self.drawn.set(self.drawn.get() + 1);
         }
     }
+
+    fn labeled_symbol(label_offset: Vec2) -> LabeledSymbol {
+        LabeledSymbol {
+            position: lon_lat(17.03664, 51.09916),
+            label: "Wrocław".to_string(),
+            symbol: '🏠',
+            style: LabeledSymbolStyle {
+                label_offset,
+                leader_line: Some(Stroke::new(1.0_f32, Color32::BLACK)),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn symbol_is_drawn_with_its_radius() {
+        let mut memory = MapMemory::default();
+        let input = RawInput {
+            screen_rect: Some(Rect::from_min_size(pos2(0., 0.), vec2(800., 600.))),
+            ..Default::default()
+        };
+
+        let output = Context::default().run(input, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.add(
+                    Map::new(None, &mut memory, lon_lat(17.03664, 51.09916))
+                        .with_plugin(Places::new(vec![labeled_symbol(vec2(8., 8.))])),
+                );
+            });
+        });
+
+        let circle = output
+            .shapes
+            .iter()
+            .find_map(|clipped| match &clipped.shape {
+                egui::Shape::Circle(circle) => Some(*circle),
+                _ => None,
+            })
+            .unwrap();
+
+        // Which is what screen_bounds and the leader line assume.
+        assert_eq!(SYMBOL_RADIUS, circle.radius);
+    }
+
+    #[test]
+    fn style_for_light_and_dark_theme() {
+        let dark = LabeledSymbolStyle::for_visuals(&Visuals::dark());
+        let light = LabeledSymbolStyle::for_visuals(&Visuals::light());
+
+        assert_ne!(dark.label_color, light.label_color);
+        assert_ne!(dark.label_background, light.label_background);
+        assert_eq!(LabeledSymbolStyle::default().label_color, dark.label_color);
+
+        // Labels are dark on light backgrounds, and the other way around.
+        assert!(light.label_color.r() < light.label_background.r());
+        assert!(dark.label_color.r() > dark.label_background.r());
+    }
+
+    #[test]
+    fn label_with_leader_line() {
+        let symbol = labeled_symbol(vec2(40., -30.));
+        let screen_position = pos2(100., 100.);
+
+        let label_rect = symbol.label_rect(screen_position, vec2(50., 10.));
+        assert_eq!(
+            Rect::from_min_max(pos2(135., 65.), pos2(195., 85.)),
+            label_rect
+        );
+
+        // Line goes from the symbol's circle to the label's nearest corner.
+        let [start, end] = symbol.leader_line(screen_position, label_rect).unwrap();
+        assert_eq!(pos2(135., 85.), end);
+        assert!((start.distance(screen_position) - SYMBOL_RADIUS).abs() < 0.001);
+        assert!(
+            (end - start)
+                .normalized()
+                .dot((end - screen_position).normalized())
+                > 0.999
+        );
+    }
+
+    #[test]
+    fn symbol_position_rounded_to_pixel() {
+        let screen = Rect::from_min_size(pos2(0., 0.), vec2(800., 600.));
+        let projector = Projector::new(screen, &MapMemory::default(), lon_lat(17.03, 51.09));
+
+        // Physical pixels are half a point in size. It takes a frame for that to be applied.
+        let ctx = Context::default();
+        ctx.set_pixels_per_point(2.);
+        let _ = ctx.run(RawInput::default(), |_| {});
+        let painter = Painter::new(ctx, egui::LayerId::background(), screen);
+
+        let mut symbol = labeled_symbol(vec2(8., 8.));
+        let unrounded = symbol.screen_position(&projector, &painter);
+        assert_ne!(unrounded, (unrounded * 2.).round() / 2.);
+
+        symbol.style.round_to_pixel = true;
+        let rounded = symbol.screen_position(&projector, &painter);
+        assert_eq!((unrounded * 2.).round() / 2., rounded);
+    }
+
+    #[test]
+    fn no_leader_line_if_label_overlaps_the_symbol() {
+        let symbol = labeled_symbol(vec2(8., 8.));
+        let screen_position = pos2(100., 100.);
+        let label_rect = symbol.label_rect(screen_position, vec2(50., 10.));
+        assert_eq!(None, symbol.leader_line(screen_position, label_rect));
+    }
+
+    #[test]
+    fn places_outside_of_the_viewport_are_not_drawn() {
+        let on_screen = Cell::new(0);
+        let off_screen = Cell::new(0);
+
+        let mut memory = MapMemory::default();
+        let input = RawInput {
+            screen_rect: Some(Rect::from_min_size(pos2(0., 0.), vec2(800., 600.))),
+            ..Default::default()
+        };
+
+        let _ = Context::default().run(input, |ctx| {
+            let places = Places::new(vec![
+                CountingPlace {
+                    position: lon_lat(17.03664, 51.09916),
+                    drawn: &on_screen,
+                },
+                CountingPlace {
+                    position: lon_lat(21.00027, 52.26470),
+                    drawn: &off_screen,
+                },
+            ]);
+
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.add(
+                    Map::new(None, &mut memory, lon_lat(17.03664, 51.09916)).with_plugin(places),
+                );
+            });
+        });
+
+        assert_eq!(1, on_screen.get());
+        assert_eq!(0, off_screen.get());
+    }
+
+    /// Place which is drawn only when zoomed in.
+    struct MinorPlace<'a>(CountingPlace<'a>);
+
+    impl Place for MinorPlace<'_> {
+        fn position(&self) -> Position {
+            self.0.position()
+        }
+
+        fn draw(&self, ui: &Ui, projector: &Projector) {
+            self.0.draw(ui, projector);
+        }
+
+        fn min_zoom(&self) -> f64 {
+            14.
+        }
+    }
+
+    #[test]
+    fn places_are_drawn_only_within_their_zoom_range() {
+        let drawn = Cell::new(0);
+        let position = lon_lat(17.03664, 51.09916);
+
+        for zoom in [10., 15.] {
+            let mut memory = MapMemory::default();
+            memory.set_zoom(zoom).unwrap();
+            let input = RawInput {
+                screen_rect: Some(Rect::from_min_size(pos2(0., 0.), vec2(800., 600.))),
+                ..Default::default()
+            };
+
+            let _ = Context::default().run(input, |ctx| {
+                let places = Places::new(vec![MinorPlace(CountingPlace {
+                    position,
+                    drawn: &drawn,
+                })]);
+
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.add(Map::new(None, &mut memory, position).with_plugin(places));
+                });
+            });
+
+            assert_eq!(if zoom < 14. { 0 } else { 1 }, drawn.get());
+        }
+    }
+
+    #[test]
+    fn different_kinds_of_places_are_drawn_together() {
+        let drawn = Cell::new(0);
+        let position = lon_lat(17.03664, 51.09916);
+
+        let mut memory = MapMemory::default();
+        memory.set_zoom(15.).unwrap();
+        let input = RawInput {
+            screen_rect: Some(Rect::from_min_size(pos2(0., 0.), vec2(800., 600.))),
+            ..Default::default()
+        };
+
+        let _ = Context::default().run(input, |ctx| {
+            let places: Places<Box<dyn Place + '_>> = Places::new(vec![
+                Box::new(CountingPlace {
+                    position,
+                    drawn: &drawn,
+                }),
+                Box::new(MinorPlace(CountingPlace {
+                    position,
+                    drawn: &drawn,
+                })),
+                Box::new(labeled_symbol(vec2(8., 8.))),
+            ]);
+
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.add(Map::new(None, &mut memory, position).with_plugin(places));
+            });
+        });
+
+        assert_eq!(2, drawn.get());
+    }
+
+    #[test]
+    fn collecting_places_from_iterator() {
+        let drawn = Cell::new(0);
+
+        let mut memory = MapMemory::default();
+        memory.set_zoom(4.).unwrap();
+        let input = RawInput {
+            screen_rect: Some(Rect::from_min_size(pos2(0., 0.), vec2(800., 600.))),
+            ..Default::default()
+        };
+
+        let _ = Context::default().run(input, |ctx| {
+            let places: Places<_> = (0..10)
+                .map(|i| CountingPlace {
+                    position: lon_lat(i as f64, 0.),
+                    drawn: &drawn,
+                })
+                .collect();
+
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.add(Map::new(None, &mut memory, lon_lat(4.5, 0.)).with_plugin(places));
+            });
+        });
+
+        assert_eq!(10, drawn.get());
+    }
+
+    #[test]
+    fn places_are_drawn_across_the_antimeridian() {
+        let drawn = Cell::new(0);
+
+        let mut memory = MapMemory::default();
+        memory.set_zoom(8.).unwrap();
+        let input = RawInput {
+            screen_rect: Some(Rect::from_min_size(pos2(0., 0.), vec2(800., 600.))),
+            ..Default::default()
+        };
+
+        let _ = Context::default().run(input, |ctx| {
+            let places = Places::new(vec![CountingPlace {
+                position: lon_lat(179., 0.),
+                drawn: &drawn,
+            }]);
+
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.add(Map::new(None, &mut memory, lon_lat(-179., 0.)).with_plugin(places));
+            });
+        });
+
+        assert_eq!(1, drawn.get());
+    }
 }
@@ -0,0 +1,127 @@
+use egui::{vec2, Align2, Color32, FontId, Pos2, Rect, Response, Ui};
+
+use crate::{Plugin, Position, PositionExt, Projector};
+
+/// [`Plugin`] which shows the geographical coordinates of the mouse pointer in the map's bottom
+/// left corner.
+#[derive(Clone)]
+pub struct CoordinateReadout {
+    precision: usize,
+    dms: bool,
+}
+
+impl Default for CoordinateReadout {
+    fn default() -> Self {
+        Self {
+            precision: 5,
+            dms: false,
+        }
+    }
+}
+
+impl CoordinateReadout {
+    /// Number of decimal places of the displayed degrees. Ignored if [`CoordinateReadout::dms`]
+    /// is enabled.
+    pub fn precision(mut self, precision: usize) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Display the coordinates as degrees, minutes and seconds, instead of decimal degrees.
+    pub fn dms(mut self, dms: bool) -> Self {
+        self.dms = dms;
+        self
+    }
+
+    /// Formatted coordinates of the `position`, latitude first.
+    fn text(&self, position: Position) -> String {
+        if self.dms {
+            let (lat, lon) = position.to_dms();
+            format!("{lat} {lon}")
+        } else {
+            format!(
+                "{:.precision$}, {:.precision$}",
+                position.y(),
+                position.x(),
+                precision = self.precision
+            )
+        }
+    }
+
+    /// Formatted coordinates of the `pointer`, which hovers over the map drawn in `rect`.
+    fn hovered_text(&self, projector: &Projector, rect: Rect, pointer: Pos2) -> String {
+        self.text(projector.unproject(pointer - rect.center()))
+    }
+}
+
+impl Plugin for CoordinateReadout {
+    fn run(self: Box<Self>, ui: &mut Ui, response: &Response, projector: &Projector) {
+        let Some(pointer) = response.hover_pos() else {
+            return;
+        };
+
+        let painter = ui.painter();
+        let text = painter.layout_no_wrap(
+            self.hovered_text(projector, response.rect, pointer),
+            FontId::monospace(12.),
+            Color32::from_gray(200),
+        );
+
+        let margin = vec2(10., 10.);
+        let text_rect = Align2::LEFT_BOTTOM.anchor_size(
+            response.rect.left_bottom() + vec2(margin.x, -margin.y),
+            text.size(),
+        );
+
+        painter.rect_filled(text_rect.expand(5.), 5., Color32::BLACK.gamma_multiply(0.8));
+        painter.galley(text_rect.min, text, Color32::BLACK);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lon_lat, MapMemory};
+    use egui::pos2;
+
+    const SCREEN: Rect = Rect::from_min_max(pos2(0., 0.), pos2(800., 600.));
+
+    #[test]
+    fn readout_of_the_hovered_position() {
+        let projector = Projector::new(SCREEN, &MapMemory::default(), lon_lat(17.03664, 51.09916));
+        let readout = CoordinateReadout::default();
+
+        assert_eq!(
+            "51.09916, 17.03664",
+            readout.hovered_text(&projector, SCREEN, SCREEN.center())
+        );
+
+        // At zoom 16, a pixel is about 0.00002° of longitude.
+        assert_eq!(
+            "51.09916, 17.03879",
+            readout.hovered_text(&projector, SCREEN, SCREEN.center() + vec2(100., 0.))
+        );
+
+        assert_eq!(
+            "51.10, 17.04",
+            readout
+                .clone()
+                .precision(2)
+                .hovered_text(&projector, SCREEN, SCREEN.center())
+        );
+    }
+
+    #[test]
+    fn readout_in_degrees_minutes_and_seconds() {
+        let readout = CoordinateReadout::default().dms(true);
+
+        assert_eq!(
+            "51°05'57\"N 17°02'12\"E",
+            readout.text(lon_lat(17.03664, 51.09916))
+        );
+        assert_eq!(
+            "33°52'04\"S 151°12'36\"W",
+            readout.text(lon_lat(-151.21, -33.8678))
+        );
+    }
+}
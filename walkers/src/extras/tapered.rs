@@ -0,0 +1,104 @@
+use egui::{Color32, Mesh, Pos2};
+
+/// Build a mesh of a line going through the `points`, which is `widths[i]` pixels wide at the
+/// `points[i]`, e.g. to draw rivers or flows whose width changes along the way. Unlike egui's
+/// strokes, the width varies smoothly between the points.
+///
+/// Points are usually obtained with [`crate::Projector::project`].
+///
+/// # Panics
+///
+/// If `points` and `widths` have different lengths.
+pub fn tapered_line(points: &[Pos2], widths: &[f32], color: Color32) -> Mesh {
+    assert_eq!(
+        points.len(),
+        widths.len(),
+        "each point needs to have its width"
+    );
+
+    let mut mesh = Mesh::default();
+    if points.len() < 2 {
+        return mesh;
+    }
+
+    for (i, (point, width)) in points.iter().zip(widths).enumerate() {
+        // Direction of the line at the point, averaged between the adjacent segments, so the
+        // strip does not break at the joints.
+        let previous = points[i.saturating_sub(1)];
+        let next = points[(i + 1).min(points.len() - 1)];
+        let normal = (next - previous).normalized().rot90() * (width / 2.);
+
+        mesh.colored_vertex(*point + normal, color);
+        mesh.colored_vertex(*point - normal, color);
+    }
+
+    for i in 0..points.len() as u32 - 1 {
+        let first = 2 * i;
+        mesh.add_triangle(first, first + 1, first + 2);
+        mesh.add_triangle(first + 1, first + 3, first + 2);
+    }
+
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{pos2, vec2, Vec2};
+
+    /// Half of the width of the `mesh` built by [`tapered_line`] at its `i`-th point.
+    fn half_width(mesh: &Mesh, i: usize) -> Vec2 {
+        (mesh.vertices[2 * i].pos - mesh.vertices[2 * i + 1].pos) / 2.
+    }
+
+    #[test]
+    fn straight_line_with_varying_width() {
+        let mesh = tapered_line(
+            &[pos2(0., 0.), pos2(10., 0.), pos2(20., 0.)],
+            &[2., 4., 6.],
+            Color32::RED,
+        );
+
+        let vertices: Vec<Pos2> = mesh.vertices.iter().map(|vertex| vertex.pos).collect();
+        assert_eq!(
+            vec![
+                pos2(0., -1.),
+                pos2(0., 1.),
+                pos2(10., -2.),
+                pos2(10., 2.),
+                pos2(20., -3.),
+                pos2(20., 3.),
+            ],
+            vertices
+        );
+        assert!(mesh
+            .vertices
+            .iter()
+            .all(|vertex| vertex.color == Color32::RED));
+
+        // Two triangles per segment.
+        assert_eq!(vec![0, 1, 2, 1, 3, 2, 2, 3, 4, 3, 5, 4], mesh.indices);
+    }
+
+    #[test]
+    fn width_is_perpendicular_to_the_line() {
+        let mesh = tapered_line(
+            &[pos2(0., 0.), pos2(10., 10.), pos2(20., 0.)],
+            &[2., 2., 2.],
+            Color32::RED,
+        );
+
+        let diagonal = std::f32::consts::FRAC_1_SQRT_2;
+        assert!((half_width(&mesh, 0) - vec2(diagonal, -diagonal)).length() < 0.001);
+
+        // At the joint, the line goes horizontally on average.
+        assert!((half_width(&mesh, 1) - vec2(0., -1.)).length() < 0.001);
+        assert!((half_width(&mesh, 2) - vec2(-diagonal, -diagonal)).length() < 0.001);
+    }
+
+    #[test]
+    fn nothing_to_draw_with_a_single_point() {
+        let mesh = tapered_line(&[pos2(0., 0.)], &[2.], Color32::RED);
+        assert!(mesh.is_empty());
+    }
+}
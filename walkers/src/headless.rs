@@ -0,0 +1,333 @@
+//! Rendering the map without a window, e.g. for thumbnails or tests.
+
+use std::collections::HashMap;
+
+use egui::{
+    epaint::{Primitive, Vertex},
+    pos2, vec2, ClippedPrimitive, Color32, ColorImage, Context, ImageData, Mesh, Pos2, RawInput,
+    Rect, TextureFilter, TextureId, TextureOptions, TexturesDelta,
+};
+
+use crate::{zoom::InvalidZoom, Map, MapMemory, Position, Tiles};
+
+/// Renders the map into a [`ColorImage`], without any window or GPU.
+///
+/// Textures are kept by the renderer itself, so the [`Tiles`] must be created with the
+/// [`HeadlessRenderer::context`]. Since there is no next frame to wait for, tiles need to be
+/// available right away, e.g. already downloaded or generated locally.
+pub struct HeadlessRenderer {
+    ctx: Context,
+    textures: HashMap<TextureId, (ColorImage, TextureOptions)>,
+}
+
+impl Default for HeadlessRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HeadlessRenderer {
+    pub fn new() -> Self {
+        Self {
+            ctx: Context::default(),
+            textures: HashMap::default(),
+        }
+    }
+
+    /// Context which should be used to create [`Tiles`] to be rendered.
+    pub fn context(&self) -> &Context {
+        &self.ctx
+    }
+
+    /// Render the map centered at `center`, with given `zoom` level, into an image of `size`
+    /// pixels.
+    pub fn render(
+        &mut self,
+        tiles: &mut dyn Tiles,
+        center: Position,
+        zoom: f64,
+        size: [usize; 2],
+    ) -> Result<ColorImage, InvalidZoom> {
+        let mut memory = MapMemory::default();
+        memory.set_zoom(zoom)?;
+        memory.center_at(center);
+
+        let input = RawInput {
+            screen_rect: Some(Rect::from_min_size(
+                Pos2::ZERO,
+                vec2(size[0] as f32, size[1] as f32),
+            )),
+            ..Default::default()
+        };
+
+        let output = self.ctx.run(input, |ctx| {
+            egui::CentralPanel::default()
+                .frame(egui::Frame::none())
+                .show(ctx, |ui| {
+                    ui.add(Map::new(Some(&mut *tiles), &mut memory, center));
+                });
+        });
+
+        let free = self.set_textures(output.textures_delta);
+
+        let mut image = ColorImage::new(size, Color32::TRANSPARENT);
+        for ClippedPrimitive {
+            clip_rect,
+            primitive,
+        } in self.ctx.tessellate(output.shapes, output.pixels_per_point)
+        {
+            if let Primitive::Mesh(mesh) = primitive {
+                self.draw_mesh(&mut image, clip_rect, &mesh);
+            }
+        }
+
+        for id in free {
+            self.textures.remove(&id);
+        }
+
+        Ok(image)
+    }
+
+    /// Apply new textures, and return the ones which should be freed after drawing.
+    fn set_textures(&mut self, delta: TexturesDelta) -> Vec<TextureId> {
+        for (id, delta) in delta.set {
+            let patch = match delta.image {
+                ImageData::Color(image) => image.as_ref().clone(),
+                ImageData::Font(image) => ColorImage {
+                    size: image.size,
+                    pixels: image.srgba_pixels(None).collect(),
+                },
+            };
+
+            match (delta.pos, self.textures.get_mut(&id)) {
+                (Some([x, y]), Some((texture, _))) => {
+                    for row in 0..patch.height() {
+                        for column in 0..patch.width() {
+                            texture[(x + column, y + row)] = patch[(column, row)];
+                        }
+                    }
+                }
+                _ => {
+                    self.textures.insert(id, (patch, delta.options));
+                }
+            }
+        }
+
+        delta.free
+    }
+
+    fn draw_mesh(&self, image: &mut ColorImage, clip_rect: Rect, mesh: &Mesh) {
+        let texture = self.textures.get(&mesh.texture_id);
+        let clip_rect = clip_rect.intersect(Rect::from_min_size(
+            Pos2::ZERO,
+            vec2(image.width() as f32, image.height() as f32),
+        ));
+
+        // Every three indices make a triangle. Incomplete one at the end, if any, is ignored.
+        for first in (0..mesh.indices.len() / 3 * 3).step_by(3) {
+            let vertices = [0, 1, 2].map(|i| &mesh.vertices[mesh.indices[first + i] as usize]);
+            draw_triangle(image, texture, clip_rect, vertices);
+        }
+    }
+}
+
+/// Twice the signed area of the triangle `a`, `b`, `p`.
+fn edge(a: Pos2, b: Pos2, p: Pos2) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+fn draw_triangle(
+    image: &mut ColorImage,
+    texture: Option<&(ColorImage, TextureOptions)>,
+    clip_rect: Rect,
+    [a, b, c]: [&Vertex; 3],
+) {
+    let area = edge(a.pos, b.pos, c.pos);
+    if area == 0.0 {
+        return;
+    }
+
+    let bounds = Rect::from_points(&[a.pos, b.pos, c.pos]).intersect(clip_rect);
+    if !bounds.is_positive() {
+        return;
+    }
+
+    for y in bounds.min.y.floor() as usize..bounds.max.y.ceil() as usize {
+        for x in bounds.min.x.floor() as usize..bounds.max.x.ceil() as usize {
+            let p = pos2(x as f32 + 0.5, y as f32 + 0.5);
+            let weights = [
+                edge(b.pos, c.pos, p) / area,
+                edge(c.pos, a.pos, p) / area,
+                edge(a.pos, b.pos, p) / area,
+            ];
+
+            if weights.iter().any(|w| *w < 0.0) {
+                continue;
+            }
+
+            let interpolate = |f: &dyn Fn(&Vertex) -> f32| {
+                weights[0] * f(a) + weights[1] * f(b) + weights[2] * f(c)
+            };
+
+            let color = Color32::from_rgba_premultiplied(
+                interpolate(&|v| v.color.r() as f32).round() as u8,
+                interpolate(&|v| v.color.g() as f32).round() as u8,
+                interpolate(&|v| v.color.b() as f32).round() as u8,
+                interpolate(&|v| v.color.a() as f32).round() as u8,
+            );
+
+            let texel = texture
+                .map(|(texture, options)| {
+                    sample(
+                        texture,
+                        options.magnification,
+                        pos2(interpolate(&|v| v.uv.x), interpolate(&|v| v.uv.y)),
+                    )
+                })
+                .unwrap_or(Color32::WHITE);
+
+            image[(x, y)] = blend(image[(x, y)], color * texel);
+        }
+    }
+}
+
+/// Sample the texture with the given `filter`, clamping at the edges. Since the map is rendered
+/// at the tiles' native resolution, minification and mipmaps are not taken into account.
+fn sample(texture: &ColorImage, filter: TextureFilter, uv: Pos2) -> Color32 {
+    let texel = |x: isize, y: isize| {
+        texture[(
+            x.clamp(0, texture.width() as isize - 1) as usize,
+            y.clamp(0, texture.height() as isize - 1) as usize,
+        )]
+    };
+
+    let x = uv.x * texture.width() as f32;
+    let y = uv.y * texture.height() as f32;
+
+    match filter {
+        TextureFilter::Nearest => texel(x.floor() as isize, y.floor() as isize),
+        TextureFilter::Linear => {
+            // Interpolate between the centers of the four nearest texels.
+            let (x, y) = (x - 0.5, y - 0.5);
+            let (left, top) = (x.floor() as isize, y.floor() as isize);
+            let (tx, ty) = (x - x.floor(), y - y.floor());
+
+            let lerp = |a: Color32, b: Color32, t: f32| {
+                let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+                Color32::from_rgba_premultiplied(
+                    channel(a.r(), b.r()),
+                    channel(a.g(), b.g()),
+                    channel(a.b(), b.b()),
+                    channel(a.a(), b.a()),
+                )
+            };
+
+            lerp(
+                lerp(texel(left, top), texel(left + 1, top), tx),
+                lerp(texel(left, top + 1), texel(left + 1, top + 1), tx),
+                ty,
+            )
+        }
+    }
+}
+
+/// Draw premultiplied `source` over `destination`.
+fn blend(destination: Color32, source: Color32) -> Color32 {
+    let remaining = 255 - source.a() as u16;
+    let channel = |s: u8, d: u8| (s as u16 + d as u16 * remaining / 255) as u8;
+    Color32::from_rgba_premultiplied(
+        channel(source.r(), destination.r()),
+        channel(source.g(), destination.g()),
+        channel(source.b(), destination.b()),
+        channel(source.a(), destination.a()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lon_lat, sources::Attribution, Texture, TextureWithUv, TileId};
+
+    /// Every tile is the same, solid red texture.
+    struct RedTiles(Texture);
+
+    impl Tiles for RedTiles {
+        fn at(&mut self, _tile_id: TileId) -> Option<TextureWithUv> {
+            Some(TextureWithUv {
+                texture: self.0.clone(),
+                uv: Rect::from_min_max(pos2(0., 0.), pos2(1., 1.)),
+            })
+        }
+
+        fn attribution(&self) -> Attribution {
+            Attribution {
+                text: "",
+                url: "",
+                logo_light: None,
+                logo_dark: None,
+            }
+        }
+
+        fn tile_size(&self) -> u32 {
+            256
+        }
+    }
+
+    #[test]
+    fn rendering_in_memory_tiles() {
+        let mut renderer = HeadlessRenderer::new();
+        let mut tiles = RedTiles(Texture::from_color_image(
+            ColorImage::new([256, 256], Color32::RED),
+            renderer.context(),
+        ));
+
+        let image = renderer
+            .render(&mut tiles, lon_lat(17.03664, 51.09916), 16., [256, 256])
+            .unwrap();
+
+        assert_eq!([256, 256], image.size);
+        assert!(image.pixels.iter().all(|pixel| *pixel == Color32::RED));
+    }
+
+    #[test]
+    fn sampling_follows_texture_filter() {
+        // Black and white texels next to each other.
+        let mut texture = ColorImage::new([2, 1], Color32::BLACK);
+        texture[(1, 0)] = Color32::WHITE;
+
+        let between = pos2(0.5, 0.5);
+        assert_eq!(
+            Color32::WHITE,
+            sample(&texture, TextureFilter::Nearest, between)
+        );
+        assert_eq!(
+            Color32::from_gray(128),
+            sample(&texture, TextureFilter::Linear, between)
+        );
+
+        // Centers of the texels are sampled exactly, and the edges are clamped.
+        for filter in [TextureFilter::Nearest, TextureFilter::Linear] {
+            assert_eq!(Color32::BLACK, sample(&texture, filter, pos2(0.25, 0.5)));
+            assert_eq!(Color32::WHITE, sample(&texture, filter, pos2(0.75, 0.5)));
+            assert_eq!(Color32::WHITE, sample(&texture, filter, pos2(1., 1.)));
+        }
+    }
+
+    #[test]
+    fn invalid_zoom_is_reported() {
+        let mut renderer = HeadlessRenderer::new();
+        let mut tiles = RedTiles(Texture::from_color_image(
+            ColorImage::new([256, 256], Color32::RED),
+            renderer.context(),
+        ));
+
+        assert_eq!(
+            Err(InvalidZoom {
+                zoom: 30.,
+                min: 0.,
+                max: 26.
+            }),
+            renderer.render(&mut tiles, lon_lat(17.03664, 51.09916), 30., [256, 256])
+        );
+    }
+}
@@ -27,7 +27,19 @@ mod web {
         if http_options.cache.is_some() {
             log::warn!("HTTP cache directory set, but ignored because, in WASM, caching is handled by the browser.");
         }
-        ClientBuilder::new(reqwest::Client::new()).build()
+        if http_options.request_timeout.is_some() || http_options.connect_timeout.is_some() {
+            log::warn!(
+                "HTTP timeouts set, but ignored because, in WASM, they are handled by the browser."
+            );
+        }
+        http_options
+            .middleware
+            .into_iter()
+            .fold(
+                ClientBuilder::new(reqwest::Client::new()),
+                ClientBuilder::with_arc,
+            )
+            .build()
     }
 }
 
@@ -83,9 +95,22 @@ mod native {
     }
 
     pub fn http_client(http_options: HttpOptions) -> ClientWithMiddleware {
-        let builder = ClientBuilder::new(reqwest::Client::new());
+        let mut client = reqwest::Client::builder();
+
+        if let Some(timeout) = http_options.request_timeout {
+            client = client.timeout(timeout);
+        }
+
+        if let Some(timeout) = http_options.connect_timeout {
+            client = client.connect_timeout(timeout);
+        }
+
+        let client = client
+            .build()
+            .expect("could not create the HTTP client, downloads will not work");
+        let builder = ClientBuilder::new(client);
 
-        if let Some(cache) = http_options.cache {
+        let builder = if let Some(cache) = http_options.cache {
             builder.with(Cache(HttpCache {
                 mode: CacheMode::Default,
                 manager: CACacheManager { path: cache },
@@ -93,7 +118,12 @@ mod native {
             }))
         } else {
             builder
-        }
-        .build()
+        };
+
+        http_options
+            .middleware
+            .into_iter()
+            .fold(builder, ClientBuilder::with_arc)
+            .build()
     }
 }
@@ -4,15 +4,19 @@
 mod center;
 mod download;
 pub mod extras;
+#[cfg(feature = "headless")]
+mod headless;
 mod io;
 mod map;
-mod mercator;
+pub mod mercator;
 pub mod sources;
 mod tiles;
 mod zoom;
 
-pub use download::{HeaderValue, HttpOptions};
-pub use map::{Map, MapMemory, Plugin, Projector};
-pub use mercator::{lat_lon, lon_lat, screen_to_position, Position, TileId};
+pub use download::{Decoder, HeaderValue, HttpOptions};
+#[cfg(feature = "headless")]
+pub use headless::HeadlessRenderer;
+pub use map::{InteractivePlugin, Map, MapMemory, Plugin, PluginEvent, Projector, ZoomAnchor};
+pub use mercator::{lat_lon, lon_lat, screen_to_position, Pixels, Position, PositionExt, TileId};
 pub use tiles::{HttpTiles, Texture, TextureWithUv, Tiles};
 pub use zoom::InvalidZoom;
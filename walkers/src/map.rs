@@ -1,10 +1,19 @@
-use std::collections::{hash_map::Entry, HashMap};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
 
-use egui::{Mesh, PointerButton, Rect, Response, Sense, Ui, UiBuilder, Vec2, Widget};
+use egui::{
+    Color32, Mesh, MultiTouchInfo, PointerButton, Pos2, Rect, Response, Sense, Ui, UiBuilder, Vec2,
+    Widget,
+};
 
 use crate::{
     center::Center,
-    mercator::{project, screen_to_position, tile_id, Pixels, PixelsExt, TileId},
+    mercator::{
+        project, screen_to_position, tile_id, total_pixels, total_tiles, Pixels, PixelsExt, TileId,
+    },
     tiles,
     zoom::{InvalidZoom, Zoom},
     Position, Tiles,
@@ -23,6 +32,59 @@ pub trait Plugin {
     /// The provided [`Response`] is the response of the map widget itself and can be used to test
     /// if the mouse is hovering or clicking on the map.
     fn run(self: Box<Self>, ui: &mut Ui, response: &Response, projector: &Projector);
+
+    /// Area of the screen where the plugin handles the pointer by itself, e.g. a button placed
+    /// within the map's `rect`. Dragging which starts there does not move the map.
+    fn gesture_exclusion(&self, _rect: Rect, _projector: &Projector) -> Option<Rect> {
+        None
+    }
+}
+
+/// Like [`Plugin`], but can also change the [`MapMemory`], e.g. to implement zoom buttons. After
+/// implementing this trait for your type, you can add it to the map with
+/// [`Map::with_interactive_plugin`].
+pub trait InteractivePlugin {
+    /// Function called at each frame, after all the [`Plugin`]s. See [`Plugin::run`] for the
+    /// description of the arguments.
+    ///
+    /// The [`Projector`] reflects the [`MapMemory`] from before any changes made here.
+    fn run(
+        self: Box<Self>,
+        ui: &mut Ui,
+        response: &Response,
+        projector: &Projector,
+        memory: &mut MapMemory,
+    );
+
+    /// See [`Plugin::gesture_exclusion`].
+    fn gesture_exclusion(&self, _rect: Rect, _projector: &Projector) -> Option<Rect> {
+        None
+    }
+}
+
+/// Something which happened in a plugin, reported with [`Projector::emit`], so the application
+/// can react to it after the map is shown, using [`MapMemory::events`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PluginEvent {
+    /// Item identified by the plugin, e.g. with its index, was clicked.
+    Clicked(usize),
+
+    /// Item identified by the plugin, e.g. with its index, is hovered.
+    Hovered(usize),
+
+    /// Any other event, described by the plugin.
+    Custom(String),
+}
+
+/// Which point of the map stays in place while zooming.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ZoomAnchor {
+    /// Location under the mouse pointer (or between the fingers).
+    #[default]
+    Cursor,
+
+    /// Center of the map.
+    Center,
 }
 
 /// The actual map widget. Instances are to be created on each frame, as all necessary state is
@@ -45,14 +107,24 @@ pub struct Map<'a, 'b, 'c> {
     tiles: Option<&'b mut dyn Tiles>,
     memory: &'a mut MapMemory,
     my_position: Position,
-    plugins: Vec<Box<dyn Plugin + 'c>>,
+    /// Plugins, together with their z-index.
+    plugins: Vec<(i32, Box<dyn Plugin + 'c>)>,
+    interactive_plugins: Vec<Box<dyn InteractivePlugin + 'c>>,
 
     zoom_gesture_enabled: bool,
     drag_gesture_enabled: bool,
-    zoom_speed: f64,
+    wheel_zoom_speed: f64,
+    pinch_zoom_speed: f64,
     double_click_to_zoom: bool,
     double_click_to_zoom_out: bool,
     zoom_with_ctrl: bool,
+    zoom_anchor: ZoomAnchor,
+    inertia_tau: f32,
+    inertia_stop_threshold: f32,
+    fling: bool,
+    snap_to_pixels: bool,
+    background: Option<Color32>,
+    on_view_changed: Option<Box<dyn FnMut(Position, f64) + 'c>>,
 }
 
 impl<'a, 'b, 'c> Map<'a, 'b, 'c> {
@@ -66,18 +138,41 @@ impl<'a, 'b, 'c> Map<'a, 'b, 'c> {
             memory,
             my_position,
             plugins: Vec::default(),
+            interactive_plugins: Vec::default(),
             zoom_gesture_enabled: true,
             drag_gesture_enabled: true,
-            zoom_speed: 2.0,
+            wheel_zoom_speed: 2.0,
+            pinch_zoom_speed: 2.0,
             double_click_to_zoom: false,
             double_click_to_zoom_out: false,
             zoom_with_ctrl: true,
+            zoom_anchor: ZoomAnchor::default(),
+            inertia_tau: 0.3,
+            inertia_stop_threshold: 0.1,
+            fling: true,
+            snap_to_pixels: false,
+            background: None,
+            on_view_changed: None,
         }
     }
 
     /// Add plugin to the drawing pipeline. Plugins allow drawing custom shapes on the map.
-    pub fn with_plugin(mut self, plugin: impl Plugin + 'c) -> Self {
-        self.plugins.push(Box::new(plugin));
+    pub fn with_plugin(self, plugin: impl Plugin + 'c) -> Self {
+        self.with_plugin_z(plugin, 0)
+    }
+
+    /// Add plugin with given z-index. Plugins with higher z-index are drawn on top of the ones
+    /// with lower z-index. Plugins with the same z-index are drawn in the order they were added.
+    /// [`Map::with_plugin`] uses z-index of 0.
+    pub fn with_plugin_z(mut self, plugin: impl Plugin + 'c, z_index: i32) -> Self {
+        self.plugins.push((z_index, Box::new(plugin)));
+        self
+    }
+
+    /// Add plugin which can change the [`MapMemory`]. Such plugins are run after all the other
+    /// ones.
+    pub fn with_interactive_plugin(mut self, plugin: impl InteractivePlugin + 'c) -> Self {
+        self.interactive_plugins.push(Box::new(plugin));
         self
     }
 
@@ -96,10 +191,23 @@ impl<'a, 'b, 'c> Map<'a, 'b, 'c> {
         self
     }
 
-    /// Change how far to zoom in/out.
+    /// Change how far to zoom in/out, both with the mouse wheel and the pinch gesture.
     /// Default value is 2.0
-    pub fn zoom_speed(mut self, speed: f64) -> Self {
-        self.zoom_speed = speed;
+    pub fn zoom_speed(self, speed: f64) -> Self {
+        self.wheel_zoom_speed(speed).pinch_zoom_speed(speed)
+    }
+
+    /// Change how far to zoom in/out with the mouse wheel (or touchpad).
+    /// Default value is 2.0
+    pub fn wheel_zoom_speed(mut self, speed: f64) -> Self {
+        self.wheel_zoom_speed = speed;
+        self
+    }
+
+    /// Change how far to zoom in/out with the pinch gesture on touch screens.
+    /// Default value is 2.0
+    pub fn pinch_zoom_speed(mut self, speed: f64) -> Self {
+        self.pinch_zoom_speed = speed;
         self
     }
 
@@ -128,6 +236,58 @@ impl<'a, 'b, 'c> Map<'a, 'b, 'c> {
         self.zoom_with_ctrl = enabled;
         self
     }
+
+    /// Set which point of the map stays in place while zooming. Default is [`ZoomAnchor::Cursor`].
+    pub fn zoom_anchor(mut self, anchor: ZoomAnchor) -> Self {
+        self.zoom_anchor = anchor;
+        self
+    }
+
+    /// Configure how the map keeps moving after being released.
+    ///
+    /// `tau` is the time constant (in seconds) of the exponential slowdown. Higher values make
+    /// the map glide longer, while `0.0` disables the inertia entirely. The movement stops once its
+    /// speed falls below `stop_threshold` fraction of the speed at the time of release.
+    ///
+    /// Default values are 0.3 and 0.1 respectively, which glide about as far as the linear
+    /// slowdown of the previous versions did at 60 FPS.
+    pub fn inertia(mut self, tau: f32, stop_threshold: f32) -> Self {
+        self.inertia_tau = tau;
+        self.inertia_stop_threshold = stop_threshold;
+        self
+    }
+
+    /// Set whether the map should keep moving for a short while after being released.
+    ///
+    /// When disabled, the map stops exactly where it was released, regardless of the
+    /// [`Map::inertia`] settings. Default is `true`.
+    pub fn fling(mut self, enabled: bool) -> Self {
+        self.fling = enabled;
+        self
+    }
+
+    /// Fill the map with the given color before drawing tiles and plugins. It shows when there
+    /// are no tiles, or they are not loaded yet.
+    pub fn background(mut self, color: Color32) -> Self {
+        self.background = Some(color);
+        self
+    }
+
+    /// Call `callback` with the new center and zoom, once the view has changed and stopped moving,
+    /// e.g. to load the data for the new viewport. It is not called while the map is being
+    /// dragged, zoomed or is still moving due to the inertia.
+    pub fn on_view_changed(mut self, callback: impl FnMut(Position, f64) + 'c) -> Self {
+        self.on_view_changed = Some(Box::new(callback));
+        self
+    }
+
+    /// Set whether tiles should be aligned to whole pixels. This eliminates the shimmering of
+    /// tiles when panning slowly, at the cost of slightly less smooth movement. Default is
+    /// `false`.
+    pub fn snap_to_pixels(mut self, enabled: bool) -> Self {
+        self.snap_to_pixels = enabled;
+        self
+    }
 }
 
 /// Projects geographical position into pixels on the viewport, suitable for [`egui::Painter`].
@@ -136,21 +296,53 @@ pub struct Projector {
     clip_rect: Rect,
     memory: MapMemory,
     my_position: Position,
+
+    /// Which copy of the world positions are projected onto. See [`Projector::world_copy`].
+    world_copy: i32,
+
+    /// Events emitted by the plugins. Shared between the copies of the projector.
+    events: Arc<Mutex<Vec<PluginEvent>>>,
 }
 
 impl Projector {
+    /// Create the projector. If [`MapMemory::set_my_position`] was used, the stored position takes
+    /// precedence over `my_position`.
     pub fn new(clip_rect: Rect, map_memory: &MapMemory, my_position: Position) -> Self {
         Self {
             clip_rect,
             memory: map_memory.to_owned(),
-            my_position,
+            my_position: map_memory.my_position.unwrap_or(my_position),
+            world_copy: 0,
+            events: Default::default(),
+        }
+    }
+
+    /// Projector for a view of its own, independent of any [`MapMemory`], showing the `center`
+    /// in the middle of the `rect`, at given `zoom`. Useful for drawing e.g. a minimap inside of
+    /// the main map.
+    pub fn for_view(rect: Rect, center: Position, zoom: f64) -> Result<Self, InvalidZoom> {
+        let mut memory = MapMemory::default();
+        memory.center_at(center);
+        memory.set_zoom(zoom)?;
+        Ok(Self::new(rect, &memory, center))
+    }
+
+    /// Projector which projects positions onto a copy of the world, shifted by `offset` times
+    /// 360° of longitude, e.g. `-1` is the copy to the west. When the viewport spans the
+    /// antimeridian, things drawn near it need to be drawn on both copies to be visible.
+    pub fn world_copy(&self, offset: i32) -> Projector {
+        Self {
+            world_copy: offset,
+            ..self.clone()
         }
     }
 
     /// Project `position` into pixels on the viewport.
     pub fn project(&self, position: Position) -> Vec2 {
-        // Turn that into a flat, mercator projection.
-        let projected_position = project(position, self.memory.zoom.into());
+        // Turn that into a flat, mercator projection, on the requested copy of the world.
+        let mut projected_position = project(position, self.memory.zoom.into());
+        let world_width = total_pixels(self.memory.zoom.into());
+        projected_position.set_x(projected_position.x() + self.world_copy as f64 * world_width);
 
         // We need the precision of f64 here,
         // since some "gaps" between tiles are noticeable on large zoom levels (e.g. 16+)
@@ -167,6 +359,40 @@ impl Projector {
             + (projected_position - map_center_projected_position).to_vec2()
     }
 
+    /// Convert a movement on the screen (e.g. a drag) which happens at the `at` position into
+    /// the difference of longitude and latitude, in degrees.
+    pub fn screen_delta_to_position_delta(&self, delta: Vec2, at: Position) -> (f64, f64) {
+        let zoom = self.memory.zoom();
+        let projected = project(at, zoom);
+        let moved = screen_to_position(
+            Pixels::new(
+                projected.x() + delta.x as f64,
+                projected.y() + delta.y as f64,
+            ),
+            zoom,
+        );
+        (moved.x() - at.x(), moved.y() - at.y())
+    }
+
+    /// Current zoom level.
+    pub fn zoom(&self) -> f64 {
+        self.memory.zoom()
+    }
+
+    /// Report an event to the application. Once the map is shown, it can be read with
+    /// [`MapMemory::events`].
+    pub fn emit(&self, event: PluginEvent) {
+        self.events
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(event);
+    }
+
+    /// Playback time set with [`MapMemory::set_time`].
+    pub fn time(&self) -> Option<f64> {
+        self.memory.time()
+    }
+
     /// Get coordinates from viewport's pixels position
     pub fn unproject(&self, position: Vec2) -> Position {
         let zoom: f64 = self.memory.zoom.into();
@@ -180,6 +406,60 @@ impl Projector {
         .position(zoom)
     }
 
+    /// Geographical positions of the north-west and south-east corners of the `rect`, e.g.
+    /// [`Ui::max_rect`].
+    pub fn viewport_bounds(&self, rect: Rect) -> (Position, Position) {
+        (
+            self.unproject(rect.left_top() - self.clip_rect.center()),
+            self.unproject(rect.right_bottom() - self.clip_rect.center()),
+        )
+    }
+
+    /// The `rect`, e.g. [`Ui::max_rect`], as a GeoJSON `Polygon` with its `bbox`.
+    #[cfg(feature = "geojson")]
+    pub fn viewport_geojson(&self, rect: Rect) -> serde_json::Value {
+        let (north_west, south_east) = self.viewport_bounds(rect);
+        let (west, north) = (north_west.x(), north_west.y());
+        let (east, south) = (south_east.x(), south_east.y());
+
+        serde_json::json!({
+            "type": "Polygon",
+            "bbox": [west, south, east, north],
+            "coordinates": [[
+                [west, north],
+                [east, north],
+                [east, south],
+                [west, south],
+                [west, north],
+            ]],
+        })
+    }
+
+    /// Whether `position` is projected within the `rect`, e.g. [`Ui::max_rect`].
+    pub fn is_visible(&self, position: Position, rect: Rect) -> bool {
+        rect.contains(self.project(position).to_pos2())
+    }
+
+    /// Distance, in pixels, between the projected `position` and `screen_point`, e.g. the mouse
+    /// pointer.
+    pub fn screen_distance(&self, position: Position, screen_point: Pos2) -> f32 {
+        self.project(position).to_pos2().distance(screen_point)
+    }
+
+    /// Find the tile of given `zoom` and `tile_size` containing the `position`, together with
+    /// the position's offset (in the tile's pixels) from the tile's top-left corner. Returns
+    /// `None` if the tiles are too large for such a low `zoom`, see [`TileId::for_position`].
+    pub fn tile_pixel(
+        &self,
+        position: Position,
+        zoom: u8,
+        tile_size: u32,
+    ) -> Option<(TileId, Vec2)> {
+        let tile_id = tile_id(position, zoom, tile_size)?;
+        let offset = project(position, zoom as f64) - tile_id.project(tile_size as f64);
+        Some((tile_id, offset.to_vec2()))
+    }
+
     /// What is the local scale of the map at the provided position and given the current zoom
     /// level?
     pub fn scale_pixel_per_meter(&self, position: Position) -> f32 {
@@ -188,12 +468,27 @@ impl Projector {
         // return f32 for ergonomics, as the result is typically used for egui code
         calculate_meters_per_pixel(position.y(), zoom) as f32
     }
+
+    /// Convert an offset of `meters` from the `position`, towards east (`x`) and north (`y`),
+    /// into an offset on the screen, e.g. to draw an ellipse of a given size in meters. Since
+    /// the Web Mercator is conformal, the scale is the same in both directions, but the screen's
+    /// `y` grows towards south. It is only accurate for distances small enough for the scale
+    /// not to change much with the latitude.
+    pub fn meters_to_pixels(&self, position: Position, meters: Vec2) -> Vec2 {
+        Vec2::new(meters.x, -meters.y) * self.scale_pixel_per_meter(position)
+    }
 }
 
 impl Map<'_, '_, '_> {
     /// Handle zoom and drag inputs, and recalculate everything accordingly.
     /// Returns `false` if no gesture handled.
-    fn handle_gestures(&mut self, ui: &mut Ui, response: &Response) -> bool {
+    fn handle_gestures(&mut self, ui: &mut Ui, response: &Response, exclusions: &[Rect]) -> bool {
+        if let Some(multi_touch) = ui.input(|input| input.multi_touch()) {
+            if ui.ui_contains_pointer() {
+                return self.handle_multi_touch(multi_touch, response);
+            }
+        }
+
         let mut zoom_delta = ui.input(|input| input.zoom_delta()) as f64;
 
         if self.double_click_to_zoom
@@ -214,7 +509,7 @@ impl Map<'_, '_, '_> {
             // We only use the raw scroll values, if we are zooming without ctrl,
             // and zoom_delta is not already over/under 1.0 (eg. a ctrl + scroll event or a pinch zoom)
             // These values seem to corrospond to the same values as one would get in `zoom_delta()`
-            zoom_delta = ui.input(|input| (1.0 + input.smooth_scroll_delta.y / 200.0)) as f64
+            zoom_delta = ui.input(|input| 1.0 + input.smooth_scroll_delta.y / 200.0) as f64
         };
 
         let mut changed = false;
@@ -226,48 +521,30 @@ impl Map<'_, '_, '_> {
             && self.zoom_gesture_enabled
         {
             // Displacement of mouse pointer relative to widget center
-            let offset = response.hover_pos().map(|p| p - response.rect.center());
-
-            let pos = self
-                .memory
-                .center_mode
-                .position(self.my_position, self.memory.zoom());
-
-            // While zooming, we want to keep the location under the mouse pointer fixed on the
-            // screen. To achieve this, we first move the location to the widget's center,
-            // then adjust zoom level, finally move the location back to the original screen
-            // position.
-            if let Some(offset) = offset {
-                self.memory.center_mode = Center::Exact(
-                    AdjustedPosition::from(pos)
-                        .shift(-offset)
-                        .zero_offset(self.memory.zoom.into()),
-                );
-            }
+            let offset = response
+                .hover_pos()
+                .map(|p| p - response.rect.center())
+                .filter(|_| self.zoom_anchor == ZoomAnchor::Cursor);
+
+            // Wheel steps are discrete, while pinch is continuous, so they might need different
+            // speeds.
+            let zoom_speed = if ui.input(|input| input.any_touches()) {
+                self.pinch_zoom_speed
+            } else {
+                self.wheel_zoom_speed
+            };
 
             // Shift by 1 because of the values given by zoom_delta(). Multiple by zoom_speed(defaults to 2.0),
             // because then it felt right with both mouse wheel, and an Android phone.
-            self.memory
-                .zoom
-                .zoom_by((zoom_delta - 1.) * self.zoom_speed);
-
-            // Recalculate the AdjustedPosition's offset, since it gets invalidated by zooming.
-            self.memory.center_mode = self
-                .memory
-                .center_mode
-                .clone()
-                .zero_offset(self.memory.zoom.into());
-
-            if let Some(offset) = offset {
-                self.memory.center_mode = self.memory.center_mode.clone().shift(offset);
-            }
+            self.zoom_around(offset, (zoom_delta - 1.) * zoom_speed);
 
             changed = true;
-        } else if self.drag_gesture_enabled {
-            changed = self
-                .memory
-                .center_mode
-                .recalculate_drag(response, self.my_position);
+        } else if self.drag_gesture_enabled && !drag_started_in(ui, exclusions) {
+            changed = self.memory.center_mode.recalculate_drag(
+                response,
+                self.my_position,
+                self.fling && self.inertia_tau > 0.0,
+            );
         }
 
         // Only enable panning with mouse_wheel if we are zooming with ctrl. But always allow touch devices to pan
@@ -277,17 +554,121 @@ impl Map<'_, '_, '_> {
             // Panning by scrolling, e.g. two-finger drag on a touchpad:
             let scroll_delta = ui.input(|i| i.smooth_scroll_delta);
             if scroll_delta != Vec2::ZERO {
-                let pos = self
-                    .memory
-                    .center_mode
-                    .position(self.my_position, self.memory.zoom());
-                self.memory.center_mode =
-                    Center::Exact(AdjustedPosition::from(pos).shift(scroll_delta));
+                self.pan(scroll_delta);
             }
         }
 
         changed
     }
+
+    /// Pinch and two-finger pan are applied together, as a single transform, so they do not
+    /// fight each other. The location which was under the fingers stays under them.
+    fn handle_multi_touch(&mut self, multi_touch: MultiTouchInfo, response: &Response) -> bool {
+        let mut changed = false;
+
+        // Single-finger drag might have been in progress, but it is now superseded by the gesture.
+        if let Center::Moving { position, .. } = &self.memory.center_mode {
+            self.memory.center_mode = Center::Exact(position.to_owned());
+        }
+
+        if self.drag_gesture_enabled && multi_touch.translation_delta != Vec2::ZERO {
+            self.pan(multi_touch.translation_delta);
+            changed = true;
+        }
+
+        if self.zoom_gesture_enabled && multi_touch.zoom_delta != 1.0 {
+            let offset = multi_touch.center_pos - response.rect.center();
+            self.zoom_around(
+                (self.zoom_anchor == ZoomAnchor::Cursor).then_some(offset),
+                (multi_touch.zoom_delta as f64 - 1.) * self.pinch_zoom_speed,
+            );
+            changed = true;
+        }
+
+        changed
+    }
+
+    /// Zoom by `delta` levels, keeping the location at `offset` (relative to the widget's
+    /// center) fixed on the screen.
+    fn zoom_around(&mut self, offset: Option<Vec2>, delta: f64) {
+        self.memory.zoom_animation = None;
+
+        let pos = self
+            .memory
+            .center_mode
+            .position(self.my_position, self.memory.zoom());
+
+        // While zooming, we want to keep the location under the mouse pointer fixed on the
+        // screen. To achieve this, we first move the location to the widget's center,
+        // then adjust zoom level, finally move the location back to the original screen
+        // position.
+        if let Some(offset) = offset {
+            self.memory.center_mode = Center::Exact(
+                AdjustedPosition::from(pos)
+                    .shift(-offset)
+                    .zero_offset(self.memory.zoom.into()),
+            );
+        }
+
+        self.memory.zoom.zoom_by(delta);
+
+        // Recalculate the AdjustedPosition's offset, since it gets invalidated by zooming.
+        self.memory.center_mode = self
+            .memory
+            .center_mode
+            .clone()
+            .zero_offset(self.memory.zoom.into());
+
+        if let Some(offset) = offset {
+            self.memory.center_mode = self.memory.center_mode.clone().shift(offset);
+        }
+    }
+
+    /// Move the map by the given number of pixels, detaching it if needed.
+    fn pan(&mut self, delta: Vec2) {
+        let pos = self
+            .memory
+            .center_mode
+            .position(self.my_position, self.memory.zoom());
+        self.memory.center_mode = Center::Exact(AdjustedPosition::from(pos).shift(delta));
+    }
+
+    /// Call the [`Map::on_view_changed`] callback if the view has settled further than
+    /// [`VIEW_CHANGE_THRESHOLD`] from where it was reported the last time.
+    fn report_view_change(&mut self) {
+        let Some(on_view_changed) = &mut self.on_view_changed else {
+            return;
+        };
+
+        let zoom = self.memory.zoom();
+        let center = self.memory.center_mode.position(self.my_position, zoom);
+
+        let changed = self
+            .memory
+            .reported_view
+            .is_some_and(|(reported, reported_zoom)| {
+                let moved_by = project(reported, zoom) - project(center, zoom);
+                reported_zoom != zoom || moved_by.x().hypot(moved_by.y()) > VIEW_CHANGE_THRESHOLD
+            });
+
+        if changed {
+            on_view_changed(center, zoom);
+        }
+
+        if changed || self.memory.reported_view.is_none() {
+            self.memory.reported_view = Some((center, zoom));
+        }
+    }
+}
+
+/// Distance, in pixels, which the map's center needs to move for [`Map::on_view_changed`] to be
+/// called.
+const VIEW_CHANGE_THRESHOLD: f64 = 1.;
+
+/// Whether the pointer was pressed within any of the `rects`.
+fn drag_started_in(ui: &Ui, rects: &[Rect]) -> bool {
+    ui.input(|input| input.pointer.press_origin())
+        .is_some_and(|origin| rects.iter().any(|rect| rect.contains(origin)))
 }
 
 impl Widget for Map<'_, '_, '_> {
@@ -295,12 +676,37 @@ impl Widget for Map<'_, '_, '_> {
         let (rect, mut response) =
             ui.allocate_exact_size(ui.available_size(), Sense::click_and_drag());
 
-        let mut moved = self.handle_gestures(ui, &response);
-        moved |= self.memory.center_mode.update_movement();
+        if let Some(my_position) = self.memory.my_position {
+            self.my_position = my_position;
+        }
+
+        let projector = Projector::new(rect, self.memory, self.my_position);
+        let exclusions: Vec<Rect> = self
+            .plugins
+            .iter()
+            .filter_map(|(_, plugin)| plugin.gesture_exclusion(rect, &projector))
+            .chain(
+                self.interactive_plugins
+                    .iter()
+                    .filter_map(|plugin| plugin.gesture_exclusion(rect, &projector)),
+            )
+            .collect();
+
+        let mut moved = self.handle_gestures(ui, &response, &exclusions);
+        moved |= self
+            .memory
+            .update_zoom_animation(ui.input(|input| input.stable_dt));
+        moved |= self.memory.center_mode.update_movement(
+            ui.input(|input| input.stable_dt),
+            self.inertia_tau,
+            self.inertia_stop_threshold,
+        );
 
         if moved {
             response.mark_changed();
             ui.ctx().request_repaint();
+        } else {
+            self.report_view_change();
         }
 
         let zoom = self.memory.zoom;
@@ -310,28 +716,79 @@ impl Widget for Map<'_, '_, '_> {
             .position(self.my_position, zoom.into());
         let painter = ui.painter().with_clip_rect(rect);
 
-        if let Some(tiles) = self.tiles {
+        if let Some(background) = self.background {
+            painter.rect_filled(rect, 0., background);
+        }
+
+        let mut map_center_projected_position = project(map_center, zoom.into());
+        if self.snap_to_pixels {
+            let pixels_per_point = ui.ctx().pixels_per_point() as f64;
+            map_center_projected_position = Pixels::new(
+                (map_center_projected_position.x() * pixels_per_point).round() / pixels_per_point,
+                (map_center_projected_position.y() * pixels_per_point).round() / pixels_per_point,
+            );
+        }
+
+        // Sources with large tiles have none at the lowest zoom levels.
+        if let Some((tiles, center_tile_id)) = self.tiles.and_then(|tiles| {
+            let center_tile_id = tile_id(map_center, zoom.round(), tiles.tile_size())?;
+            Some((tiles, center_tile_id))
+        }) {
             let mut meshes = Default::default();
             flood_fill_tiles(
                 painter.clip_rect(),
-                tile_id(map_center, zoom.round(), tiles.tile_size()),
-                project(map_center, zoom.into()),
+                center_tile_id,
+                map_center_projected_position,
                 zoom.into(),
                 tiles,
                 &mut meshes,
             );
 
-            for shape in meshes.drain().filter_map(|(_, mesh)| mesh) {
-                painter.add(shape);
+            for mut mesh in meshes.drain().filter_map(|(_, mesh)| mesh) {
+                // Center alone is not enough, as tiles are of fractional size between zoom
+                // levels, and the viewport's center might be in the middle of a pixel.
+                if self.snap_to_pixels {
+                    for vertex in &mut mesh.vertices {
+                        vertex.pos = painter.round_pos_to_pixels(vertex.pos);
+                    }
+                }
+                painter.add(mesh);
             }
         }
 
-        let projector = Projector::new(response.rect, self.memory, self.my_position);
-        for (idx, plugin) in self.plugins.into_iter().enumerate() {
+        let mut projector = Projector::new(response.rect, self.memory, self.my_position);
+        if self.snap_to_pixels {
+            // So plugins stay in place relative to the tiles.
+            projector.memory.center_at(screen_to_position(
+                map_center_projected_position,
+                zoom.into(),
+            ));
+        }
+        let plugins_count = self.plugins.len();
+
+        // Stable sort, so the insertion order is kept for the same z-index.
+        self.plugins.sort_by_key(|(z_index, _)| *z_index);
+
+        for (idx, (_, plugin)) in self.plugins.into_iter().enumerate() {
             let mut child_ui = ui.new_child(UiBuilder::new().max_rect(rect).id_salt(idx));
+            child_ui.set_clip_rect(rect);
             plugin.run(&mut child_ui, &response, &projector);
         }
 
+        for (idx, plugin) in self.interactive_plugins.into_iter().enumerate() {
+            let mut child_ui =
+                ui.new_child(UiBuilder::new().max_rect(rect).id_salt(plugins_count + idx));
+            child_ui.set_clip_rect(rect);
+            plugin.run(&mut child_ui, &response, &projector, self.memory);
+        }
+
+        self.memory.events = std::mem::take(
+            &mut projector
+                .events
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        );
+
         response
     }
 }
@@ -387,33 +844,134 @@ impl From<Position> for AdjustedPosition {
 pub struct MapMemory {
     center_mode: Center,
     zoom: Zoom,
+    time: Option<f64>,
+    zoom_animation: Option<ZoomAnimation>,
+    events: Vec<PluginEvent>,
+    my_position: Option<Position>,
+
+    /// View last reported to [`Map::on_view_changed`].
+    reported_view: Option<(Position, f64)>,
+}
+
+/// Zooming started with [`MapMemory::zoom_to`].
+#[derive(Debug, Clone)]
+struct ZoomAnimation {
+    from: f64,
+    to: f64,
+    elapsed: f32,
+    duration: f32,
 }
 
 impl MapMemory {
     /// Try to zoom in, returning `Err(InvalidZoom)` if already at maximum.
     pub fn zoom_in(&mut self) -> Result<(), InvalidZoom> {
+        self.zoom_animation = None;
         self.center_mode = self.center_mode.clone().zero_offset(self.zoom.into());
         self.zoom.zoom_in()
     }
 
     /// Try to zoom out, returning `Err(InvalidZoom)` if already at minimum.
     pub fn zoom_out(&mut self) -> Result<(), InvalidZoom> {
+        self.zoom_animation = None;
         self.center_mode = self.center_mode.clone().zero_offset(self.zoom.into());
         self.zoom.zoom_out()
     }
 
     /// Set exact zoom level
     pub fn set_zoom(&mut self, zoom: f64) -> Result<(), InvalidZoom> {
+        self.zoom_animation = None;
+        self.set_zoom_keeping_animation(zoom)
+    }
+
+    fn set_zoom_keeping_animation(&mut self, zoom: f64) -> Result<(), InvalidZoom> {
         self.center_mode = self.center_mode.clone().zero_offset(self.zoom.into());
         self.zoom = Zoom::try_from(zoom)?;
         Ok(())
     }
 
+    /// Try to zoom in, keeping the `position` fixed on the screen, e.g. to get closer to a search
+    /// result. If the map follows `my_position`, it keeps doing so.
+    pub fn zoom_in_at(&mut self, position: Position) -> Result<(), InvalidZoom> {
+        self.zoom_at(position, self.zoom() + 1.)
+    }
+
+    /// Try to zoom out, keeping the `position` fixed on the screen. If the map follows
+    /// `my_position`, it keeps doing so.
+    pub fn zoom_out_at(&mut self, position: Position) -> Result<(), InvalidZoom> {
+        self.zoom_at(position, self.zoom() - 1.)
+    }
+
+    fn zoom_at(&mut self, position: Position, zoom: f64) -> Result<(), InvalidZoom> {
+        let new_zoom = Zoom::try_from(zoom)?;
+        self.zoom_animation = None;
+
+        if let Some(center) = self.detached() {
+            // Distance between the position and the center must stay the same on the screen.
+            let offset = project(position, self.zoom()) - project(center, self.zoom());
+            self.center_mode =
+                Center::Exact(AdjustedPosition::new(position, offset).zero_offset(zoom));
+        }
+
+        self.zoom = new_zoom;
+        Ok(())
+    }
+
+    /// Smoothly change the zoom level to `zoom`, over the `duration`. The animation is cancelled
+    /// by any other zooming.
+    pub fn zoom_to(&mut self, zoom: f64, duration: Duration) -> Result<(), InvalidZoom> {
+        Zoom::try_from(zoom)?;
+        self.zoom_animation = Some(ZoomAnimation {
+            from: self.zoom(),
+            to: zoom,
+            elapsed: 0.,
+            duration: duration.as_secs_f32(),
+        });
+        Ok(())
+    }
+
+    /// Advance the zoom animation by `dt` seconds. Returns `true` if zoom has changed.
+    pub(crate) fn update_zoom_animation(&mut self, dt: f32) -> bool {
+        let Some(animation) = &mut self.zoom_animation else {
+            return false;
+        };
+
+        animation.elapsed += dt;
+        let progress = if animation.duration > 0. {
+            (animation.elapsed / animation.duration).min(1.)
+        } else {
+            1.
+        };
+        let zoom = animation.from + (animation.to - animation.from) * progress as f64;
+
+        if progress >= 1. {
+            self.zoom_animation = None;
+        }
+
+        // Both ends were validated, so everything in between is valid as well.
+        self.set_zoom_keeping_animation(zoom).is_ok()
+    }
+
+    /// Events emitted by the plugins while the map was shown last time.
+    pub fn events(&self) -> &[PluginEvent] {
+        &self.events
+    }
+
     /// Returns the current zoom level
     pub fn zoom(&self) -> f64 {
         self.zoom.into()
     }
 
+    /// Current zoom level, rounded to the nearest integer, e.g. for querying data for the tiles.
+    pub fn zoom_level(&self) -> u8 {
+        self.zoom.round()
+    }
+
+    /// Number of tiles spanning the world horizontally (and vertically) at the
+    /// [`MapMemory::zoom_level`].
+    pub fn world_tiles(&self) -> u32 {
+        total_tiles(self.zoom_level())
+    }
+
     /// Returns exact position if map is detached (i.e. not following `my_position`),
     /// `None` otherwise.
     pub fn detached(&self) -> Option<Position> {
@@ -432,6 +990,34 @@ impl MapMemory {
     pub fn follow_my_position(&mut self) {
         self.center_mode = Center::MyPosition;
     }
+
+    /// Store `my_position` in the memory, so it is used instead of the one given to [`Map::new`].
+    pub fn set_my_position(&mut self, my_position: Position) {
+        self.my_position = Some(my_position);
+    }
+
+    /// Forget the position stored with [`MapMemory::set_my_position`], so the one given to
+    /// [`Map::new`] is used again.
+    pub fn clear_my_position(&mut self) {
+        self.my_position = None;
+    }
+
+    /// Position set with [`MapMemory::set_my_position`], if any.
+    pub fn my_position(&self) -> Option<Position> {
+        self.my_position
+    }
+
+    /// Set the playback time, which plugins can get with [`Projector::time`] to draw e.g. the
+    /// frame of an animation. Unit is up to the application, e.g. seconds since the start of a
+    /// recorded track.
+    pub fn set_time(&mut self, time: f64) {
+        self.time = Some(time);
+    }
+
+    /// Returns the playback time, if it was set.
+    pub fn time(&self) -> Option<f64> {
+        self.time
+    }
 }
 
 /// Use simple [flood fill algorithm](https://en.wikipedia.org/wiki/Flood_fill) to draw tiles on the map.
@@ -496,6 +1082,791 @@ fn calculate_meters_per_pixel(latitude: f64, zoom: f64) -> f64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{lon_lat, Texture, TextureWithUv};
+    use egui::{pos2, Context, Event, RawInput, TouchDeviceId, TouchId, TouchPhase};
+    use std::{
+        cell::{Cell, RefCell},
+        rc::Rc,
+    };
+
+    const SCREEN: Rect = Rect::from_min_max(pos2(0., 0.), pos2(800., 600.));
+
+    fn show_map(ctx: &Context, memory: &mut MapMemory, events: Vec<Event>) {
+        show_configured_map(ctx, memory, events, |map| map);
+    }
+
+    fn show_configured_map(
+        ctx: &Context,
+        memory: &mut MapMemory,
+        events: Vec<Event>,
+        configure: impl for<'a, 'b, 'c> Fn(Map<'a, 'b, 'c>) -> Map<'a, 'b, 'c>,
+    ) {
+        let input = RawInput {
+            screen_rect: Some(SCREEN),
+            events,
+            ..Default::default()
+        };
+
+        let _ = ctx.run(input, |ctx| {
+            egui::CentralPanel::default()
+                .frame(egui::Frame::none())
+                .show(ctx, |ui| {
+                    ui.add(configure(Map::new(
+                        None,
+                        memory,
+                        lon_lat(17.03664, 51.09916),
+                    )));
+                });
+        });
+    }
+
+    fn touch(id: u64, phase: TouchPhase, pos: egui::Pos2) -> Event {
+        Event::Touch {
+            device_id: TouchDeviceId(0),
+            id: TouchId(id),
+            phase,
+            pos,
+            force: None,
+        }
+    }
+
+    fn drag(
+        ctx: &Context,
+        memory: &mut MapMemory,
+        configure: impl for<'a, 'b, 'c> Fn(Map<'a, 'b, 'c>) -> Map<'a, 'b, 'c>,
+    ) {
+        let button = |pos, pressed| Event::PointerButton {
+            pos,
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: Default::default(),
+        };
+
+        let frames = [
+            vec![Event::PointerMoved(pos2(400., 300.))],
+            vec![button(pos2(400., 300.), true)],
+            vec![Event::PointerMoved(pos2(420., 300.))],
+            vec![Event::PointerMoved(pos2(440., 300.))],
+            vec![Event::PointerMoved(pos2(460., 300.))],
+            vec![button(pos2(460., 300.), false)],
+        ];
+
+        for events in frames {
+            show_configured_map(ctx, memory, events, &configure);
+        }
+    }
+
+    #[test]
+    fn released_map_keeps_moving_due_to_inertia() {
+        let ctx = Context::default();
+        let mut memory = MapMemory::default();
+
+        drag(&ctx, &mut memory, |map| map);
+        assert!(matches!(memory.center_mode, Center::Inertia { .. }));
+    }
+
+    #[test]
+    fn released_map_stops_if_inertia_is_disabled() {
+        let ctx = Context::default();
+        let mut memory = MapMemory::default();
+
+        drag(&ctx, &mut memory, |map| map.inertia(0.0, 0.1));
+        assert!(matches!(memory.center_mode, Center::Exact(_)));
+    }
+
+    #[test]
+    fn released_map_stops_if_fling_is_disabled() {
+        let ctx = Context::default();
+        let mut memory = MapMemory::default();
+
+        drag(&ctx, &mut memory, |map| map.fling(false));
+        assert!(matches!(memory.center_mode, Center::Exact(_)));
+
+        // Nothing is animating, so no more repaints are needed.
+        assert!(!memory.center_mode.update_movement(1. / 60., 0.2, 0.1));
+    }
+
+    #[test]
+    fn pinch_and_pan_are_applied_together() {
+        let ctx = Context::default();
+        let mut memory = MapMemory::default();
+
+        // The spot which is going to be under the fingers.
+        let fingers_before = pos2(390., 300.);
+        let position = Projector::new(SCREEN, &memory, lon_lat(17.03664, 51.09916))
+            .unproject(fingers_before - SCREEN.center());
+
+        show_map(&ctx, &mut memory, vec![Event::PointerMoved(fingers_before)]);
+        show_map(
+            &ctx,
+            &mut memory,
+            vec![
+                touch(0, TouchPhase::Start, pos2(365., 300.)),
+                touch(1, TouchPhase::Start, pos2(415., 300.)),
+            ],
+        );
+
+        // Fingers spread apart and move right at the same time.
+        show_map(
+            &ctx,
+            &mut memory,
+            vec![
+                touch(0, TouchPhase::Move, pos2(365., 300.)),
+                touch(1, TouchPhase::Move, pos2(435., 300.)),
+            ],
+        );
+
+        assert!(memory.zoom() > 16.);
+
+        // Position which was under the fingers, moved with them.
+        let fingers_after = pos2(400., 300.);
+        let projected = Projector::new(SCREEN, &memory, lon_lat(17.03664, 51.09916))
+            .project(position)
+            .to_pos2();
+        assert!(
+            projected.distance(fingers_after) < 1.,
+            "{projected:?} vs {fingers_after:?}"
+        );
+    }
+
+    #[test]
+    fn wheel_and_pinch_have_separate_zoom_speeds() {
+        fn configure<'a, 'b, 'c>(map: Map<'a, 'b, 'c>) -> Map<'a, 'b, 'c> {
+            map.wheel_zoom_speed(1.).pinch_zoom_speed(4.)
+        }
+
+        let ctx = Context::default();
+
+        // Wheel (with ctrl) zooming by 10%.
+        let mut wheel_memory = MapMemory::default();
+        let before = wheel_memory.zoom();
+        for events in [
+            vec![Event::PointerMoved(pos2(400., 300.))],
+            vec![Event::Zoom(1.1)],
+        ] {
+            show_configured_map(&ctx, &mut wheel_memory, events, configure);
+        }
+        approx::assert_relative_eq!(before + 0.1, wheel_memory.zoom(), epsilon = 0.001);
+
+        // Fingers spreading by 10%.
+        let ctx = Context::default();
+        let mut pinch_memory = MapMemory::default();
+        for events in [
+            vec![Event::PointerMoved(pos2(400., 300.))],
+            vec![
+                touch(0, TouchPhase::Start, pos2(350., 300.)),
+                touch(1, TouchPhase::Start, pos2(450., 300.)),
+            ],
+            vec![
+                touch(0, TouchPhase::Move, pos2(345., 300.)),
+                touch(1, TouchPhase::Move, pos2(455., 300.)),
+            ],
+        ] {
+            show_configured_map(&ctx, &mut pinch_memory, events, configure);
+        }
+        approx::assert_relative_eq!(before + 0.4, pinch_memory.zoom(), epsilon = 0.001);
+    }
+
+    fn zoom_with_pointer_off_center(anchor: ZoomAnchor) -> MapMemory {
+        let ctx = Context::default();
+        let mut memory = MapMemory::default();
+        memory.center_at(lon_lat(17.03664, 51.09916));
+
+        for events in [
+            vec![Event::PointerMoved(pos2(100., 100.))],
+            vec![Event::Zoom(1.5)],
+        ] {
+            show_configured_map(&ctx, &mut memory, events, |map| map.zoom_anchor(anchor));
+        }
+
+        assert!(memory.zoom() > 16.);
+        memory
+    }
+
+    #[test]
+    fn zoom_anchored_at_the_center_keeps_the_center() {
+        let memory = zoom_with_pointer_off_center(ZoomAnchor::Center);
+        let center = memory.detached().unwrap();
+        approx::assert_relative_eq!(17.03664, center.x(), epsilon = 1e-9);
+        approx::assert_relative_eq!(51.09916, center.y(), epsilon = 1e-9);
+    }
+
+    #[test]
+    fn zoom_anchored_at_the_cursor_moves_the_center() {
+        let memory = zoom_with_pointer_off_center(ZoomAnchor::Cursor);
+        let center = memory.detached().unwrap();
+        assert!(center.x() < 17.03664);
+        assert!(center.y() > 51.09916);
+    }
+
+    /// Every tile is the same, blank texture.
+    struct BlankTiles(Texture);
+
+    impl Tiles for BlankTiles {
+        fn at(&mut self, _tile_id: TileId) -> Option<TextureWithUv> {
+            Some(TextureWithUv {
+                texture: self.0.clone(),
+                uv: Rect::from_min_max(pos2(0., 0.), pos2(1., 1.)),
+            })
+        }
+
+        fn attribution(&self) -> crate::sources::Attribution {
+            crate::sources::Attribution {
+                text: "",
+                url: "",
+                logo_light: None,
+                logo_dark: None,
+            }
+        }
+
+        fn tile_size(&self) -> u32 {
+            256
+        }
+    }
+
+    /// Positions of all vertices of the tiles drawn.
+    fn tile_vertices(snap_to_pixels: bool, screen: Rect, zoom: f64) -> Vec<Pos2> {
+        let ctx = Context::default();
+        let mut tiles = BlankTiles(Texture::from_color_image(
+            egui::ColorImage::new([256, 256], egui::Color32::WHITE),
+            &ctx,
+        ));
+        let mut memory = MapMemory::default();
+        memory.set_zoom(zoom).unwrap();
+
+        let output = ctx.run(
+            RawInput {
+                screen_rect: Some(screen),
+                ..Default::default()
+            },
+            |ctx| {
+                egui::CentralPanel::default()
+                    .frame(egui::Frame::none())
+                    .show(ctx, |ui| {
+                        ui.add(
+                            Map::new(Some(&mut tiles), &mut memory, lon_lat(17.03664, 51.09916))
+                                .snap_to_pixels(snap_to_pixels),
+                        );
+                    });
+            },
+        );
+
+        output
+            .shapes
+            .into_iter()
+            .filter_map(|clipped| match clipped.shape {
+                egui::Shape::Mesh(mesh) => Some(mesh.vertices),
+                _ => None,
+            })
+            .flatten()
+            .map(|vertex| vertex.pos)
+            .collect()
+    }
+
+    #[test]
+    fn tiles_are_aligned_to_pixels_if_snapping() {
+        let is_aligned = |pos: &Pos2| pos.x.fract() == 0. && pos.y.fract() == 0.;
+
+        let odd_screen = Rect::from_min_size(pos2(0., 0.), Vec2::new(801., 601.));
+
+        for (screen, zoom) in [
+            (SCREEN, 16.),
+            (SCREEN, 16.3),
+            (odd_screen, 16.),
+            (odd_screen, 16.3),
+        ] {
+            let vertices = tile_vertices(true, screen, zoom);
+            assert!(!vertices.is_empty());
+            assert!(
+                vertices.iter().all(is_aligned),
+                "{screen:?} {zoom} {vertices:?}"
+            );
+
+            let vertices = tile_vertices(false, screen, zoom);
+            assert!(!vertices.iter().all(is_aligned));
+        }
+    }
+
+    /// Plugin which records where a position is projected.
+    struct RecordingProjection(Position, Rc<Cell<Option<Vec2>>>);
+
+    impl Plugin for RecordingProjection {
+        fn run(self: Box<Self>, _ui: &mut Ui, _response: &Response, projector: &Projector) {
+            self.1.set(Some(projector.project(self.0)));
+        }
+    }
+
+    #[test]
+    fn plugins_are_aligned_to_pixels_if_snapping() {
+        let ctx = Context::default();
+        let mut memory = MapMemory::default();
+        let projected = Rc::new(Cell::new(None));
+
+        // Corner of the tile in the middle of the map, which is drawn at a whole pixel.
+        let tile_corner = screen_to_position(
+            tile_id(lon_lat(17.03664, 51.09916), 16, 256)
+                .unwrap()
+                .project(256.),
+            16.,
+        );
+
+        show_configured_map(&ctx, &mut memory, vec![], |map| {
+            map.snap_to_pixels(true)
+                .with_plugin(RecordingProjection(tile_corner, projected.clone()))
+        });
+
+        let projected = projected.get().unwrap();
+        approx::assert_abs_diff_eq!(projected.x, projected.x.round(), epsilon = 1e-3);
+        approx::assert_abs_diff_eq!(projected.y, projected.y.round(), epsilon = 1e-3);
+    }
+
+    #[test]
+    fn background_is_filled_if_set() {
+        let sea = egui::Color32::from_rgb(170, 211, 223);
+        let has_background = |background: Option<egui::Color32>| {
+            let mut memory = MapMemory::default();
+            let output = Context::default().run(
+                RawInput {
+                    screen_rect: Some(SCREEN),
+                    ..Default::default()
+                },
+                |ctx| {
+                    egui::CentralPanel::default()
+                        .frame(egui::Frame::none())
+                        .show(ctx, |ui| {
+                            let map = Map::new(None, &mut memory, lon_lat(17.03664, 51.09916));
+                            ui.add(match background {
+                                Some(color) => map.background(color),
+                                None => map,
+                            });
+                        });
+                },
+            );
+
+            output.shapes.iter().any(|clipped| {
+                matches!(&clipped.shape, egui::Shape::Rect(rect) if rect.fill == sea && rect.rect == SCREEN)
+            })
+        };
+
+        assert!(has_background(Some(sea)));
+        assert!(!has_background(None));
+    }
+
+    #[test]
+    fn projecting_into_a_sub_rect() {
+        let minimap = Rect::from_min_max(pos2(600., 400.), pos2(800., 550.));
+        let projector = Projector::for_view(minimap, lon_lat(0., 0.), 2.).unwrap();
+
+        assert_eq!(
+            minimap.center(),
+            projector.project(lon_lat(0., 0.)).to_pos2()
+        );
+
+        // At zoom 2, the world is 1024 pixels wide, so 90° is a quarter of it.
+        assert_eq!(
+            minimap.center() + Vec2::new(256., 0.),
+            projector.project(lon_lat(90., 0.)).to_pos2()
+        );
+        assert_eq!(2., projector.zoom());
+
+        assert!(Projector::for_view(minimap, lon_lat(0., 0.), 30.).is_err());
+    }
+
+    #[cfg(feature = "geojson")]
+    #[test]
+    fn viewport_as_geojson() {
+        let mut memory = MapMemory::default();
+        memory.set_zoom(2.).unwrap();
+
+        // At zoom 2, the world is 1024 pixels wide, so this is a half of it.
+        let rect = Rect::from_min_size(pos2(0., 0.), Vec2::splat(512.));
+        let projector = Projector::new(rect, &memory, lon_lat(0., 0.));
+        let geojson = projector.viewport_geojson(rect);
+
+        let north = std::f64::consts::FRAC_PI_2.sinh().atan().to_degrees();
+        let expected = [
+            [-90., north],
+            [90., north],
+            [90., -north],
+            [-90., -north],
+            [-90., north],
+        ];
+
+        assert_eq!("Polygon", geojson["type"]);
+
+        let bbox: Vec<f64> = serde_json::from_value(geojson["bbox"].clone()).unwrap();
+        for (expected, actual) in [-90., -north, 90., north].iter().zip(bbox) {
+            approx::assert_abs_diff_eq!(*expected, actual, epsilon = 1e-9);
+        }
+
+        let coordinates: Vec<Vec<[f64; 2]>> =
+            serde_json::from_value(geojson["coordinates"].clone()).unwrap();
+        assert_eq!(1, coordinates.len());
+        assert_eq!(5, coordinates[0].len());
+        for (expected, actual) in expected.iter().zip(&coordinates[0]) {
+            approx::assert_abs_diff_eq!(expected[0], actual[0], epsilon = 1e-9);
+            approx::assert_abs_diff_eq!(expected[1], actual[1], epsilon = 1e-9);
+        }
+    }
+
+    #[test]
+    fn map_is_clipped_to_its_rect() {
+        let ctx = Context::default();
+        let mut tiles = BlankTiles(Texture::from_color_image(
+            egui::ColorImage::new([256, 256], egui::Color32::WHITE),
+            &ctx,
+        ));
+        let mut memory = MapMemory::default();
+
+        let output = ctx.run(
+            RawInput {
+                screen_rect: Some(SCREEN),
+                ..Default::default()
+            },
+            |ctx| {
+                egui::CentralPanel::default()
+                    .frame(egui::Frame::none())
+                    .show(ctx, |ui| {
+                        ui.add(Map::new(
+                            Some(&mut tiles),
+                            &mut memory,
+                            lon_lat(17.03664, 51.09916),
+                        ));
+                    });
+            },
+        );
+
+        let clip_rects: Vec<Rect> = output
+            .shapes
+            .iter()
+            .filter(|clipped| matches!(clipped.shape, egui::Shape::Mesh(_)))
+            .map(|clipped| clipped.clip_rect)
+            .collect();
+        assert!(!clip_rects.is_empty());
+        assert!(clip_rects.iter().all(|rect| *rect == SCREEN));
+    }
+
+    /// Plugin which records its name when run.
+    struct Named(&'static str, Rc<RefCell<Vec<&'static str>>>);
+
+    impl Plugin for Named {
+        fn run(self: Box<Self>, _ui: &mut Ui, _response: &Response, _projector: &Projector) {
+            self.1.borrow_mut().push(self.0);
+        }
+    }
+
+    #[test]
+    fn plugins_are_run_according_to_their_z_index() {
+        let ctx = Context::default();
+        let mut memory = MapMemory::default();
+        let order = Rc::new(RefCell::new(Vec::new()));
+
+        show_configured_map(&ctx, &mut memory, vec![], |map| {
+            map.with_plugin_z(Named("labels", order.clone()), 10)
+                .with_plugin(Named("first", order.clone()))
+                .with_plugin_z(Named("background", order.clone()), -1)
+                .with_plugin(Named("second", order.clone()))
+        });
+
+        assert_eq!(
+            vec!["background", "first", "second", "labels"],
+            *order.borrow()
+        );
+    }
+
+    /// Plugin which handles the pointer by itself in the given area.
+    struct Excluding(Rect);
+
+    impl Plugin for Excluding {
+        fn run(self: Box<Self>, _ui: &mut Ui, _response: &Response, _projector: &Projector) {}
+
+        fn gesture_exclusion(&self, _rect: Rect, _projector: &Projector) -> Option<Rect> {
+            Some(self.0)
+        }
+    }
+
+    impl InteractivePlugin for Excluding {
+        fn run(
+            self: Box<Self>,
+            _ui: &mut Ui,
+            _response: &Response,
+            _projector: &Projector,
+            _memory: &mut MapMemory,
+        ) {
+        }
+
+        fn gesture_exclusion(&self, _rect: Rect, _projector: &Projector) -> Option<Rect> {
+            Some(self.0)
+        }
+    }
+
+    #[test]
+    fn drag_starting_in_excluded_area_does_not_move_the_map() {
+        let ctx = Context::default();
+        let mut memory = MapMemory::default();
+
+        drag(&ctx, &mut memory, |map| {
+            map.with_plugin(Excluding(Rect::from_center_size(
+                pos2(400., 300.),
+                Vec2::splat(10.),
+            )))
+        });
+        assert!(memory.detached().is_none());
+
+        // Same for the interactive plugins.
+        drag(&ctx, &mut memory, |map| {
+            map.with_interactive_plugin(Excluding(Rect::from_center_size(
+                pos2(400., 300.),
+                Vec2::splat(10.),
+            )))
+        });
+        assert!(memory.detached().is_none());
+
+        // Excluded area does not matter if drag starts elsewhere.
+        drag(&ctx, &mut memory, |map| {
+            map.with_plugin(Excluding(Rect::from_center_size(
+                pos2(200., 200.),
+                Vec2::splat(10.),
+            )))
+        });
+        assert!(memory.detached().is_some());
+    }
+
+    type Views = Rc<RefCell<Vec<(Position, f64)>>>;
+
+    /// Map which records the views reported to [`Map::on_view_changed`].
+    fn recording_views<'a, 'b, 'c>(map: Map<'a, 'b, 'c>, views: &Views) -> Map<'a, 'b, 'c> {
+        let views = views.clone();
+        map.fling(false)
+            .on_view_changed(move |center, zoom| views.borrow_mut().push((center, zoom)))
+    }
+
+    #[test]
+    fn view_change_is_reported_once_the_map_stops() {
+        let ctx = Context::default();
+        let mut memory = MapMemory::default();
+        let views = Views::default();
+
+        // Initial view is not a change.
+        show_configured_map(&ctx, &mut memory, vec![], |map| {
+            recording_views(map, &views)
+        });
+        assert!(views.borrow().is_empty());
+
+        drag(&ctx, &mut memory, |map| recording_views(map, &views));
+        assert!(views.borrow().is_empty(), "reported while still moving");
+
+        for _ in 0..2 {
+            show_configured_map(&ctx, &mut memory, vec![], |map| {
+                recording_views(map, &views)
+            });
+        }
+
+        let center = memory.detached().unwrap();
+        assert_eq!(vec![(center, memory.zoom())], *views.borrow());
+    }
+
+    /// Plugin which remembers the playback time.
+    struct Clock(Rc<Cell<Option<f64>>>);
+
+    impl Plugin for Clock {
+        fn run(self: Box<Self>, _ui: &mut Ui, _response: &Response, projector: &Projector) {
+            self.0.set(projector.time());
+        }
+    }
+
+    #[test]
+    fn plugins_receive_the_playback_time() {
+        let ctx = Context::default();
+        let mut memory = MapMemory::default();
+        let time = Rc::new(Cell::new(None));
+
+        show_configured_map(&ctx, &mut memory, vec![], |map| {
+            map.with_plugin(Clock(time.clone()))
+        });
+        assert_eq!(None, time.get());
+
+        memory.set_time(12.5);
+        show_configured_map(&ctx, &mut memory, vec![], |map| {
+            map.with_plugin(Clock(time.clone()))
+        });
+        assert_eq!(Some(12.5), time.get());
+    }
+
+    struct ZoomIn;
+
+    impl InteractivePlugin for ZoomIn {
+        fn run(
+            self: Box<Self>,
+            _ui: &mut Ui,
+            _response: &Response,
+            _projector: &Projector,
+            memory: &mut MapMemory,
+        ) {
+            memory.zoom_in().unwrap();
+        }
+    }
+
+    /// Plugin which reports a click on the map.
+    struct ReportingClicks;
+
+    impl Plugin for ReportingClicks {
+        fn run(self: Box<Self>, _ui: &mut Ui, response: &Response, projector: &Projector) {
+            if response.clicked() {
+                projector.emit(PluginEvent::Clicked(3));
+            }
+            projector
+                .world_copy(1)
+                .emit(PluginEvent::Custom("shown".to_string()));
+        }
+    }
+
+    #[test]
+    fn projector_can_be_sent_to_another_thread() {
+        fn assert_send<T: Send>() {}
+        assert_send::<Projector>();
+    }
+
+    #[test]
+    fn events_emitted_by_plugins_are_available_in_memory() {
+        let ctx = Context::default();
+        let mut memory = MapMemory::default();
+        let click = |pressed| Event::PointerButton {
+            pos: pos2(100., 100.),
+            button: PointerButton::Primary,
+            pressed,
+            modifiers: Default::default(),
+        };
+
+        for events in [
+            vec![Event::PointerMoved(pos2(100., 100.))],
+            vec![click(true)],
+        ] {
+            show_configured_map(&ctx, &mut memory, events, |map| {
+                map.with_plugin(ReportingClicks)
+            });
+            assert_eq!(&[PluginEvent::Custom("shown".to_string())], memory.events());
+        }
+
+        show_configured_map(&ctx, &mut memory, vec![click(false)], |map| {
+            map.with_plugin(ReportingClicks)
+        });
+        assert_eq!(
+            &[
+                PluginEvent::Clicked(3),
+                PluginEvent::Custom("shown".to_string())
+            ],
+            memory.events()
+        );
+
+        // Events are only kept until the next frame.
+        show_configured_map(&ctx, &mut memory, vec![], |map| map);
+        assert!(memory.events().is_empty());
+    }
+
+    #[test]
+    fn interactive_plugin_changes_memory() {
+        let ctx = Context::default();
+        let mut memory = MapMemory::default();
+        let zoom = memory.zoom();
+
+        show_configured_map(&ctx, &mut memory, vec![], |map| {
+            map.with_interactive_plugin(ZoomIn)
+        });
+
+        assert_eq!(zoom + 1., memory.zoom());
+    }
+
+    #[test]
+    fn visibility_of_positions() {
+        let center = lon_lat(17.03664, 51.09916);
+        let projector = Projector::new(SCREEN, &MapMemory::default(), center);
+
+        assert!(projector.is_visible(center, SCREEN));
+        assert!(!projector.is_visible(lon_lat(21.00027, 52.26470), SCREEN));
+    }
+
+    #[test]
+    fn distance_on_the_screen() {
+        let center = lon_lat(17.03664, 51.09916);
+        let projector = Projector::new(SCREEN, &MapMemory::default(), center);
+
+        assert_eq!(0., projector.screen_distance(center, SCREEN.center()));
+        assert_eq!(
+            50.,
+            projector.screen_distance(center, SCREEN.center() + Vec2::new(30., 40.))
+        );
+    }
+
+    #[test]
+    fn position_across_the_antimeridian_is_visible_on_a_world_copy() {
+        let mut memory = MapMemory::default();
+        memory.center_at(lon_lat(-179., 0.));
+        memory.set_zoom(8.).unwrap();
+        let projector = Projector::new(SCREEN, &memory, lon_lat(0., 0.));
+
+        let position = lon_lat(179., 0.);
+        assert!(!projector.is_visible(position, SCREEN));
+        assert!(projector.world_copy(-1).is_visible(position, SCREEN));
+        assert!(!projector.world_copy(1).is_visible(position, SCREEN));
+    }
+
+    #[test]
+    fn screen_delta_into_position_delta() {
+        let center = lon_lat(17.03664, 51.09916);
+        let projector = Projector::new(SCREEN, &MapMemory::default(), center);
+
+        let from = Vec2::new(100., 50.);
+        let delta = Vec2::new(12., -7.);
+        let start = projector.unproject(from);
+        let end = projector.unproject(from + delta);
+
+        let (lon, lat) = projector.screen_delta_to_position_delta(delta, start);
+        approx::assert_relative_eq!(end.x() - start.x(), lon, max_relative = 1e-6);
+        approx::assert_relative_eq!(end.y() - start.y(), lat, max_relative = 1e-6);
+
+        // Moving right and up increases both longitude and latitude.
+        assert!(lon > 0.);
+        assert!(lat > 0.);
+    }
+
+    #[test]
+    fn position_within_a_tile() {
+        let position = lon_lat(17.03664, 51.09916);
+        let projector = Projector::new(SCREEN, &MapMemory::default(), position);
+
+        let (tile_id, offset) = projector.tile_pixel(position, 16, 256).unwrap();
+        assert_eq!(
+            TileId {
+                x: 35869,
+                y: 21911,
+                zoom: 16
+            },
+            tile_id
+        );
+        assert!(
+            (offset - Vec2::new(108.97, 67.72)).length() < 0.01,
+            "{offset:?}"
+        );
+
+        // Larger tiles cover more area, so they are of lower zoom.
+        let (tile_id, offset) = projector.tile_pixel(position, 16, 512).unwrap();
+        assert_eq!(
+            TileId {
+                x: 17934,
+                y: 10955,
+                zoom: 15
+            },
+            tile_id
+        );
+        assert!(
+            (offset - Vec2::new(364.97, 323.72)).length() < 0.01,
+            "{offset:?}"
+        );
+
+        // There are no 512px tiles at zoom 0.
+        assert!(projector.tile_pixel(position, 0, 512).is_none());
+    }
 
     fn assert_approx_eq(a: f64, b: f64) {
         let diff = (a - b).abs();
@@ -506,6 +1877,185 @@ mod tests {
         );
     }
 
+    #[test]
+    fn zoom_animation_converges() {
+        let mut memory = MapMemory::default();
+        let center = lon_lat(17.03664, 51.09916);
+        memory.center_at(center);
+        memory.zoom_to(18., Duration::from_secs(1)).unwrap();
+
+        let mut steps = 0;
+        while memory.update_zoom_animation(0.1) {
+            steps += 1;
+            if steps == 5 {
+                approx::assert_relative_eq!(17., memory.zoom(), epsilon = 0.001);
+            }
+        }
+
+        assert_eq!(10, steps);
+        assert_eq!(18., memory.zoom());
+        let detached = memory.detached().unwrap();
+        approx::assert_relative_eq!(center.x(), detached.x(), max_relative = 1e-9);
+        approx::assert_relative_eq!(center.y(), detached.y(), max_relative = 1e-9);
+    }
+
+    #[test]
+    fn zooming_at_position_keeps_it_in_place() {
+        let mut memory = MapMemory::default();
+        memory.center_at(lon_lat(17.03664, 51.09916));
+        let anchor = lon_lat(17.04, 51.1);
+        let screen_position = |memory: &MapMemory| {
+            Projector::new(SCREEN, memory, lon_lat(0., 0.))
+                .project(anchor)
+                .to_pos2()
+        };
+
+        let before = screen_position(&memory);
+        assert!(before.distance(SCREEN.center()) > 100.);
+
+        memory.zoom_in_at(anchor).unwrap();
+        assert_eq!(17., memory.zoom());
+        assert!(screen_position(&memory).distance(before) < 0.01);
+
+        memory.zoom_out_at(anchor).unwrap();
+        memory.zoom_out_at(anchor).unwrap();
+        assert_eq!(15., memory.zoom());
+        assert!(screen_position(&memory).distance(before) < 0.01);
+
+        memory.set_zoom(26.).unwrap();
+        assert!(memory.zoom_in_at(anchor).is_err());
+    }
+
+    /// Plugin which records the position at the center of the map.
+    struct RecordingCenter(Rc<Cell<Option<Position>>>);
+
+    impl Plugin for RecordingCenter {
+        fn run(self: Box<Self>, _ui: &mut Ui, _response: &Response, projector: &Projector) {
+            self.0.set(Some(projector.unproject(Vec2::ZERO)));
+        }
+    }
+
+    #[test]
+    fn my_position_stored_in_memory_is_followed() {
+        let ctx = Context::default();
+        let mut memory = MapMemory::default();
+        let center = Rc::new(Cell::new(None));
+
+        let assert_centered_at = |center: &Rc<Cell<Option<Position>>>, expected: Position| {
+            let actual = center.get().unwrap();
+            approx::assert_relative_eq!(expected.x(), actual.x(), max_relative = 1e-6);
+            approx::assert_relative_eq!(expected.y(), actual.y(), max_relative = 1e-6);
+        };
+
+        // Position given to Map::new by show_configured_map.
+        show_configured_map(&ctx, &mut memory, vec![], |map| {
+            map.with_plugin(RecordingCenter(center.clone()))
+        });
+        assert_centered_at(&center, lon_lat(17.03664, 51.09916));
+
+        memory.set_my_position(lon_lat(21.00027, 52.26470));
+        show_configured_map(&ctx, &mut memory, vec![], |map| {
+            map.with_plugin(RecordingCenter(center.clone()))
+        });
+        assert_centered_at(&center, lon_lat(21.00027, 52.26470));
+        assert_eq!(Some(lon_lat(21.00027, 52.26470)), memory.my_position());
+
+        memory.set_my_position(lon_lat(19.94498, 50.06465));
+        show_configured_map(&ctx, &mut memory, vec![], |map| {
+            map.with_plugin(RecordingCenter(center.clone()))
+        });
+        assert_centered_at(&center, lon_lat(19.94498, 50.06465));
+
+        // Back to the one given to Map::new.
+        memory.clear_my_position();
+        show_configured_map(&ctx, &mut memory, vec![], |map| {
+            map.with_plugin(RecordingCenter(center.clone()))
+        });
+        assert_centered_at(&center, lon_lat(17.03664, 51.09916));
+        assert_eq!(None, memory.my_position());
+    }
+
+    #[test]
+    fn zooming_at_position_keeps_following_my_position() {
+        let mut memory = MapMemory::default();
+        memory.zoom_in_at(lon_lat(17.04, 51.1)).unwrap();
+        assert_eq!(17., memory.zoom());
+        assert_eq!(None, memory.detached());
+    }
+
+    #[test]
+    fn integer_zoom_level_and_world_tiles() {
+        let mut memory = MapMemory::default();
+
+        memory.set_zoom(3.4).unwrap();
+        assert_eq!(3, memory.zoom_level());
+        assert_eq!(8, memory.world_tiles());
+
+        memory.set_zoom(3.6).unwrap();
+        assert_eq!(4, memory.zoom_level());
+        assert_eq!(16, memory.world_tiles());
+
+        memory.set_zoom(0.).unwrap();
+        assert_eq!(0, memory.zoom_level());
+        assert_eq!(1, memory.world_tiles());
+    }
+
+    #[test]
+    fn zooming_cancels_zoom_animation() {
+        let mut memory = MapMemory::default();
+        memory.zoom_to(18., Duration::from_secs(1)).unwrap();
+        memory.update_zoom_animation(0.1);
+        memory.set_zoom(10.).unwrap();
+
+        assert!(!memory.update_zoom_animation(0.1));
+        assert_eq!(10., memory.zoom());
+    }
+
+    #[test]
+    fn zooming_to_invalid_level() {
+        let mut memory = MapMemory::default();
+        assert_eq!(
+            Err(InvalidZoom {
+                zoom: 30.,
+                min: 0.,
+                max: 26.
+            }),
+            memory.zoom_to(30., Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn meters_to_pixels_match_projection_at_high_latitude() {
+        const EARTH_RADIUS: f64 = 40_075_016.686 / (2. * std::f64::consts::PI);
+
+        let mut memory = MapMemory::default();
+        memory.set_zoom(10.).unwrap();
+        let position = lon_lat(25., 70.);
+        let projector = Projector::new(SCREEN, &memory, position);
+
+        // Move 100 meters to the east and to the north.
+        let meters = 100.;
+        let east = lon_lat(
+            position.x() + (meters / (EARTH_RADIUS * position.y().to_radians().cos())).to_degrees(),
+            position.y(),
+        );
+        let north = lon_lat(
+            position.x(),
+            position.y() + (meters / EARTH_RADIUS).to_degrees(),
+        );
+
+        let moved_east = projector.project(east) - projector.project(position);
+        let moved_north = projector.project(north) - projector.project(position);
+
+        let converted = projector.meters_to_pixels(position, Vec2::new(meters as f32, 0.));
+        approx::assert_relative_eq!(moved_east.x, converted.x, max_relative = 0.001);
+        approx::assert_abs_diff_eq!(0., converted.y);
+
+        let converted = projector.meters_to_pixels(position, Vec2::new(0., meters as f32));
+        approx::assert_abs_diff_eq!(0., converted.x);
+        approx::assert_relative_eq!(moved_north.y, converted.y, max_relative = 0.001);
+    }
+
     #[test]
     fn test_equator_zoom_0() {
         // At zoom 0 (whole world), equator should be about 156.5km per pixel
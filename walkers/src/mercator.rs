@@ -23,13 +23,144 @@ pub fn lon_lat(lon: f64, lat: f64) -> Position {
     Position::new(lon, lat)
 }
 
+/// Latitude beyond which the Web Mercator is cut off, making the whole world a square.
+const MAX_LATITUDE: f64 = 85.0511287798066;
+
+/// Methods of [`Position`]: sanitizing it, e.g. when coming from external data, formatting it as
+/// degrees, minutes and seconds, and projecting it onto the Web Mercator.
+pub trait PositionExt {
+    /// Whether the latitude is within the Web Mercator's limit of ±85.0511°, and the longitude
+    /// within ±180°.
+    fn is_valid(&self) -> bool;
+
+    /// Position with the latitude clamped to ±85.0511°, and the longitude normalized to
+    /// -180°..180°.
+    fn clamped(&self) -> Position;
+
+    /// Latitude and longitude formatted as degrees, minutes and seconds, e.g. `51°05'57"N` and
+    /// `17°02'12"E`.
+    fn to_dms(&self) -> (String, String);
+
+    /// Project the position onto the "World bitmap" at the given `zoom`, using the Web Mercator.
+    /// The result is in pixels, with (0, 0) at the north-western corner of the world, and
+    /// 256 * 2^zoom pixels on each side. Use [`screen_to_position`] for the reverse.
+    fn project(&self, zoom: f64) -> Pixels;
+}
+
+impl PositionExt for Position {
+    fn is_valid(&self) -> bool {
+        (-MAX_LATITUDE..=MAX_LATITUDE).contains(&self.y()) && (-180.0..=180.0).contains(&self.x())
+    }
+
+    fn clamped(&self) -> Position {
+        let lon = if (-180.0..=180.0).contains(&self.x()) {
+            self.x()
+        } else {
+            (self.x() + 180.).rem_euclid(360.) - 180.
+        };
+
+        lon_lat(lon, self.y().clamp(-MAX_LATITUDE, MAX_LATITUDE))
+    }
+
+    fn to_dms(&self) -> (String, String) {
+        (dms(self.y(), ['N', 'S']), dms(self.x(), ['E', 'W']))
+    }
+
+    fn project(&self, zoom: f64) -> Pixels {
+        project(*self, zoom)
+    }
+}
+
+/// Parse a position written as degrees, minutes and seconds, latitude first, e.g.
+/// `51°05'57"N 17°02'12"E`. Seconds may be fractional. Reverse of [`PositionExt::to_dms`].
+pub fn from_dms(text: &str) -> Option<Position> {
+    let text = text.trim();
+    let split = text.find(['N', 'S'])? + 1;
+    let (lat, lon) = text.split_at(split);
+
+    let lat = parse_dms(lat, ['N', 'S'], 90.)?;
+    let lon = parse_dms(lon.trim_start_matches([' ', ',']), ['E', 'W'], 180.)?;
+
+    Some(lat_lon(lat, lon))
+}
+
+/// Average of the `positions`, treating longitude and latitude as plain numbers. Good enough
+/// for small areas, but not across the antimeridian. Returns `None` if `positions` is empty.
+pub fn centroid(positions: &[Position]) -> Option<Position> {
+    let sum = positions.iter().copied().reduce(|a, b| a + b)?;
+    Some(sum / positions.len() as f64)
+}
+
+/// Average of the `positions` on the surface of a sphere, which is correct anywhere on the
+/// globe, including across the antimeridian. Returns `None` if `positions` is empty, or their
+/// average is undefined, e.g. for two antipodal positions.
+pub fn spherical_centroid(positions: &[Position]) -> Option<Position> {
+    // Average the positions as unit vectors pointing from the center of the sphere.
+    let [x, y, z] = positions.iter().fold([0., 0., 0.], |[x, y, z], position| {
+        let (lon, lat) = (position.x().to_radians(), position.y().to_radians());
+        [
+            x + lat.cos() * lon.cos(),
+            y + lat.cos() * lon.sin(),
+            z + lat.sin(),
+        ]
+    });
+
+    // Averaged vectors cancel out, e.g. for antipodal positions.
+    if (x * x + y * y + z * z).sqrt() < 1e-9 {
+        return None;
+    }
+
+    Some(lon_lat(
+        y.atan2(x).to_degrees(),
+        z.atan2((x * x + y * y).sqrt()).to_degrees(),
+    ))
+}
+
+/// Format `degrees` as degrees, minutes and whole seconds, followed by the hemisphere.
+fn dms(degrees: f64, [positive, negative]: [char; 2]) -> String {
+    let hemisphere = if degrees < 0. { negative } else { positive };
+    let seconds = (degrees.abs() * 3600.).round() as u64;
+    format!(
+        "{}°{:02}'{:02}\"{}",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60,
+        hemisphere
+    )
+}
+
+/// Parse a single `D°M'S"H` coordinate, which must not exceed `max` degrees.
+fn parse_dms(text: &str, [positive, negative]: [char; 2], max: f64) -> Option<f64> {
+    let text = text.trim();
+    let (text, sign) = if let Some(text) = text.strip_suffix(positive) {
+        (text, 1.)
+    } else {
+        (text.strip_suffix(negative)?, -1.)
+    };
+
+    let (degrees, rest) = text.split_once('°')?;
+    let (minutes, rest) = rest.split_once('\'')?;
+    let seconds = rest.strip_suffix('"')?;
+
+    let degrees: u32 = degrees.trim().parse().ok()?;
+    let minutes: u32 = minutes.trim().parse().ok()?;
+    let seconds: f64 = seconds.trim().parse().ok()?;
+
+    if minutes >= 60 || !(0. ..60.).contains(&seconds) {
+        return None;
+    }
+
+    let value = degrees as f64 + minutes as f64 / 60. + seconds / 3600.;
+    (value <= max).then_some(sign * value)
+}
+
 /// Zoom specifies how many pixels are in the whole map. For example, zoom 0 means that the whole
 /// map is just one 256x256 tile, zoom 1 means that it is 2x2 tiles, and so on.
 pub(crate) fn total_pixels(zoom: f64) -> f64 {
     2f64.powf(zoom) * (TILE_SIZE as f64)
 }
 
-pub fn total_tiles(zoom: u8) -> u32 {
+pub(crate) fn total_tiles(zoom: u8) -> u32 {
     2u32.pow(zoom as u32)
 }
 
@@ -41,7 +172,7 @@ pub type Pixels = geo_types::Point;
 
 use std::f64::consts::PI;
 
-pub trait PixelsExt {
+pub(crate) trait PixelsExt {
     fn to_vec2(&self) -> egui::Vec2;
 }
 
@@ -79,6 +210,14 @@ pub struct TileId {
 }
 
 impl TileId {
+    /// Tile containing the `position` at the given `zoom`, for a source providing tiles of
+    /// `tile_size` pixels. Sources with larger tiles have them one zoom level lower, just like
+    /// [`crate::Tiles`] expect them. Returns `None` if the tiles are too large for such a low
+    /// `zoom`, e.g. 512px tiles at zoom 0.
+    pub fn for_position(position: Position, zoom: u8, tile_size: u32) -> Option<TileId> {
+        tile_id(position, zoom, tile_size)
+    }
+
     /// Tile position (in pixels) on the "World bitmap".
     pub fn project(&self, tile_size: f64) -> Pixels {
         Pixels::new(self.x as f64 * tile_size, self.y as f64 * tile_size)
@@ -117,20 +256,22 @@ impl TileId {
     }
 }
 
-/// Calculate the tile coordinated for the given position.
-pub(crate) fn tile_id(position: Position, mut zoom: u8, source_tile_size: u32) -> TileId {
+/// Calculate the tile coordinated for the given position. Returns `None` if the source's tiles are
+/// too large for such a low `zoom`.
+pub(crate) fn tile_id(position: Position, zoom: u8, source_tile_size: u32) -> Option<TileId> {
     let (x, y) = mercator_normalized(position);
 
     // Some sources provide larger tiles, effectively bundling e.g. 4 256px tiles in one
     // 512px one. Walkers uses 256px internally, so we need to adjust the zoom level.
-    zoom -= (source_tile_size as f64 / TILE_SIZE as f64).log2() as u8;
+    let zoom = zoom.checked_sub((source_tile_size as f64 / TILE_SIZE as f64).log2() as u8)?;
 
-    // Map that into a big bitmap made out of web tiles.
-    let number_of_tiles = 2u32.pow(zoom as u32) as f64;
-    let x = (x * number_of_tiles).floor() as u32;
-    let y = (y * number_of_tiles).floor() as u32;
+    // Map that into a big bitmap made out of web tiles. Positions at the very edge, e.g. 180° of
+    // longitude, belong to the last tile.
+    let number_of_tiles = total_tiles(zoom);
+    let x = ((x * number_of_tiles as f64).floor() as u32).min(number_of_tiles - 1);
+    let y = ((y * number_of_tiles as f64).floor() as u32).min(number_of_tiles - 1);
 
-    TileId { x, y, zoom }
+    Some(TileId { x, y, zoom })
 }
 
 /// Project geographical position into a 2D plane using Mercator.
@@ -140,7 +281,8 @@ pub(crate) fn project(position: Position, zoom: f64) -> Pixels {
     Pixels::new(x * total_pixels, y * total_pixels)
 }
 
-/// Transforms screen pixels into a geographical position.
+/// Geographical position of the `pixels` on the "World bitmap" at the given `zoom`. Reverse of
+/// [`PositionExt::project`].
 pub fn screen_to_position(pixels: Pixels, zoom: f64) -> Position {
     let number_of_pixels: f64 = 2f64.powf(zoom) * (TILE_SIZE as f64);
 
@@ -149,7 +291,9 @@ pub fn screen_to_position(pixels: Pixels, zoom: f64) -> Position {
     let lon = (lon * 2. - 1.) * PI;
     let lon = lon.to_degrees();
 
-    let lat = pixels.y();
+    // Beyond the world bitmap, there is nothing to unproject. Latitude would quickly reach
+    // extreme values there, so it is kept within the Web Mercator's limits instead.
+    let lat = pixels.y().clamp(0., number_of_pixels);
     let lat = lat / number_of_pixels;
     let lat = (-lat * 2. + 1.) * PI;
     let lat = lat.sinh().atan().to_degrees();
@@ -175,7 +319,7 @@ mod tests {
                 y: 345104,
                 zoom
             },
-            tile_id(citadel, zoom, 256)
+            tile_id(citadel, zoom, 256).unwrap()
         );
 
         // Automatically zooms out for larger tiles
@@ -185,13 +329,13 @@ mod tests {
                 y: 172552,
                 zoom: zoom - 1
             },
-            tile_id(citadel, zoom, 512)
+            tile_id(citadel, zoom, 512).unwrap()
         );
 
         // Projected tile is just its x, y multiplied by the size of tiles.
         assert_eq!(
             Pixels::new(585455. * 256., 345104. * 256.),
-            tile_id(citadel, zoom, 256).project(256.)
+            tile_id(citadel, zoom, 256).unwrap().project(256.)
         );
 
         // Projected Citadel position should be somewhere near projected tile, shifted only by the
@@ -212,6 +356,233 @@ mod tests {
         approx::assert_relative_eq!(calculated.y(), citadel.y(), max_relative = 1.0);
     }
 
+    #[test]
+    fn public_projection_matches_internal_one() {
+        let citadel = lon_lat(21.00027, 52.26470);
+
+        for zoom in [0., 10.5, 20.] {
+            let projected = citadel.project(zoom);
+            assert_eq!(project(citadel, zoom), projected);
+
+            let calculated = screen_to_position(projected, zoom);
+            approx::assert_relative_eq!(calculated.x(), citadel.x(), max_relative = 1e-9);
+            approx::assert_relative_eq!(calculated.y(), citadel.y(), max_relative = 1e-9);
+        }
+
+        // The whole world is a single 256px tile at zoom 0.
+        assert_eq!(Pixels::new(128., 128.), lon_lat(0., 0.).project(0.));
+    }
+
+    #[test]
+    fn clamping_latitude() {
+        let position = lat_lon(200., 17.03664);
+        assert!(!position.is_valid());
+
+        let clamped = position.clamped();
+        assert!(clamped.is_valid());
+        assert_eq!(lat_lon(MAX_LATITUDE, 17.03664), clamped);
+        assert_eq!(
+            lat_lon(-MAX_LATITUDE, 17.03664),
+            lat_lon(-90., 17.03664).clamped()
+        );
+    }
+
+    #[test]
+    fn normalizing_longitude() {
+        let position = lat_lon(51.09916, 190.);
+        assert!(!position.is_valid());
+
+        let normalized = position.clamped();
+        assert!(normalized.is_valid());
+        approx::assert_relative_eq!(-170., normalized.x());
+        approx::assert_relative_eq!(
+            17.03664,
+            lat_lon(51.09916, 377.03664).clamped().x(),
+            max_relative = 1e-9
+        );
+    }
+
+    #[test]
+    fn formatting_dms() {
+        assert_eq!(
+            ("51°05'57\"N".to_string(), "17°02'12\"E".to_string()),
+            lat_lon(51.09916, 17.03664).to_dms()
+        );
+        assert_eq!(
+            ("33°52'04\"S".to_string(), "151°12'36\"W".to_string()),
+            lat_lon(-33.8678, -151.21).to_dms()
+        );
+        assert_eq!(
+            ("0°00'00\"N".to_string(), "0°00'00\"E".to_string()),
+            lat_lon(0., 0.).to_dms()
+        );
+
+        // Rounding up to a full minute and degree.
+        assert_eq!(
+            ("1°00'00\"N".to_string(), "180°00'00\"W".to_string()),
+            lat_lon(0.99999, -179.99999).to_dms()
+        );
+    }
+
+    #[test]
+    fn parsing_dms() {
+        let position = from_dms("51°05'57\"N 17°02'12\"E").unwrap();
+        approx::assert_relative_eq!(51.0991667, position.y(), max_relative = 1e-6);
+        approx::assert_relative_eq!(17.0366667, position.x(), max_relative = 1e-6);
+
+        let position = from_dms(" 33° 52' 4.5\"S, 151°12'36\"W ").unwrap();
+        approx::assert_relative_eq!(-33.8679167, position.y(), max_relative = 1e-6);
+        approx::assert_relative_eq!(-151.21, position.x(), max_relative = 1e-6);
+
+        assert_eq!(Some(lat_lon(0., 0.)), from_dms("0°00'00\"N 0°00'00\"E"));
+        assert_eq!(
+            Some(lat_lon(-90., 180.)),
+            from_dms("90°00'00\"S 180°00'00\"E")
+        );
+    }
+
+    #[test]
+    fn parsing_invalid_dms() {
+        for text in [
+            "",
+            "51°05'57\"N",
+            "17°02'12\"E 51°05'57\"N",
+            "51°05'57\"N 17°02'12\"N",
+            "51°60'00\"N 17°02'12\"E",
+            "51°05'60\"N 17°02'12\"E",
+            "91°00'00\"N 17°02'12\"E",
+            "51°05'57\"N 181°00'00\"E",
+            "-51°05'57\"N 17°02'12\"E",
+            "51°05'57\"N 17°02'12\"E trailing",
+        ] {
+            assert_eq!(None, from_dms(text), "{text}");
+        }
+    }
+
+    #[test]
+    fn dms_round_trip() {
+        for position in [
+            lat_lon(51.09916, 17.03664),
+            lat_lon(-33.8678, -151.21),
+            lat_lon(0., -0.5),
+            lat_lon(-90., 180.),
+        ] {
+            let (lat, lon) = position.to_dms();
+            let parsed = from_dms(&format!("{lat} {lon}")).unwrap();
+
+            // Seconds are rounded, which is up to half of 1/3600th of a degree.
+            approx::assert_abs_diff_eq!(position.y(), parsed.y(), epsilon = 0.5 / 3600.);
+            approx::assert_abs_diff_eq!(position.x(), parsed.x(), epsilon = 0.5 / 3600.);
+        }
+    }
+
+    #[test]
+    fn centroid_of_positions() {
+        assert_eq!(None, centroid(&[]));
+        assert_eq!(
+            Some(lat_lon(51., 18.)),
+            centroid(&[lat_lon(50., 17.), lat_lon(52., 19.)])
+        );
+
+        // Plain average does not know about the antimeridian.
+        assert_eq!(
+            Some(lat_lon(10., 0.)),
+            centroid(&[lat_lon(10., 179.), lat_lon(10., -179.)])
+        );
+    }
+
+    #[test]
+    fn spherical_centroid_of_positions() {
+        assert_eq!(None, spherical_centroid(&[]));
+        assert_eq!(
+            None,
+            spherical_centroid(&[lat_lon(0., 0.), lat_lon(0., 180.)])
+        );
+
+        let centroid = spherical_centroid(&[lat_lon(50., 17.), lat_lon(52., 19.)]).unwrap();
+        // Close to the plain average, as the area is small.
+        approx::assert_abs_diff_eq!(51., centroid.y(), epsilon = 0.05);
+        approx::assert_abs_diff_eq!(18., centroid.x(), epsilon = 0.05);
+
+        // Straddling the antimeridian, the centroid lands on it, not on the other side of the
+        // globe.
+        let centroid = spherical_centroid(&[lat_lon(10., 179.), lat_lon(10., -179.)]).unwrap();
+        approx::assert_abs_diff_eq!(180., centroid.x().abs(), epsilon = 1e-9);
+        approx::assert_abs_diff_eq!(10., centroid.y(), epsilon = 0.01);
+    }
+
+    #[test]
+    fn unprojecting_beyond_the_world() {
+        let zoom = 3.;
+
+        let above = screen_to_position(Pixels::new(100., -1e6), zoom);
+        assert!(above.y().is_finite());
+        approx::assert_relative_eq!(MAX_LATITUDE, above.y(), max_relative = 1e-9);
+
+        let below = screen_to_position(Pixels::new(100., 1e12), zoom);
+        assert!(below.y().is_finite());
+        approx::assert_relative_eq!(-MAX_LATITUDE, below.y(), max_relative = 1e-9);
+    }
+
+    #[test]
+    fn tile_for_position() {
+        let citadel = lon_lat(21.00027, 52.26470);
+
+        assert_eq!(
+            TileId {
+                x: 585455,
+                y: 345104,
+                zoom: 20
+            },
+            TileId::for_position(citadel, 20, 256).unwrap()
+        );
+
+        assert_eq!(
+            TileId {
+                x: 292727,
+                y: 172552,
+                zoom: 19
+            },
+            TileId::for_position(citadel, 20, 512).unwrap()
+        );
+
+        // There is only one tile at zoom 0.
+        assert_eq!(
+            TileId {
+                x: 0,
+                y: 0,
+                zoom: 0
+            },
+            TileId::for_position(citadel, 0, 256).unwrap()
+        );
+
+        // Larger tiles start one zoom level lower, so there are none at zoom 0.
+        assert_eq!(None, TileId::for_position(citadel, 0, 512));
+        assert_eq!(None, TileId::for_position(citadel, 1, 1024));
+    }
+
+    #[test]
+    fn tile_for_position_at_the_edge_of_the_world() {
+        assert_eq!(
+            Some(TileId {
+                x: 0,
+                y: 0,
+                zoom: 3
+            }),
+            TileId::for_position(lon_lat(-180., MAX_LATITUDE), 3, 256)
+        );
+
+        // Belongs to the last tile, not to a non-existent one beyond it.
+        assert_eq!(
+            Some(TileId {
+                x: 7,
+                y: 7,
+                zoom: 3
+            }),
+            TileId::for_position(lon_lat(180., -MAX_LATITUDE), 3, 256)
+        );
+    }
+
     #[test]
     fn tile_id_cannot_go_beyond_limits() {
         // There is only one tile at zoom 0.
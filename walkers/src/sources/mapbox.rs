@@ -38,32 +38,42 @@ impl MapboxStyle {
 pub struct Mapbox {
     /// Predefined style to use
     pub style: MapboxStyle,
-    /// Render tiles at 1024x1024 instead of 512x512 (@2x)
+    /// Render tiles at 1024x1024 instead of 512x512 (@2x), even on displays which are not HiDPI
     pub high_resolution: bool,
     /// Mapbox API key, required
     pub access_token: String,
 }
 
-impl TileSource for Mapbox {
-    fn tile_url(&self, tile_id: TileId) -> String {
+impl Mapbox {
+    fn url(&self, tile_id: TileId, high_resolution: bool) -> String {
         format!(
             "https://api.mapbox.com/styles/v1/mapbox/{}/tiles/512/{}/{}/{}{}?access_token={}",
             self.style.api_slug(),
             tile_id.zoom,
             tile_id.x,
             tile_id.y,
-            if self.high_resolution { "@2x" } else { "" },
+            if high_resolution { "@2x" } else { "" },
             self.access_token
         )
     }
+}
+
+impl TileSource for Mapbox {
+    fn tile_url(&self, tile_id: TileId) -> String {
+        self.url(tile_id, self.high_resolution)
+    }
+
+    fn hidpi_tile_url(&self, tile_id: TileId) -> Option<String> {
+        Some(self.url(tile_id, true))
+    }
 
     fn attribution(&self) -> Attribution {
         // TODO: Proper linking (https://docs.mapbox.com/help/getting-started/attribution/))
         Attribution {
             text: "© Mapbox, © OpenStreetMap",
             url: "https://www.mapbox.com/about/maps/",
-            logo_light: Some(egui::include_image!("../../assets/mapbox-logo-white.svg")),
-            logo_dark: Some(egui::include_image!("../../assets/mapbox-logo-black.svg")),
+            logo_light: Some(egui::include_image!("../../assets/mapbox-logo-black.svg")),
+            logo_dark: Some(egui::include_image!("../../assets/mapbox-logo-white.svg")),
         }
     }
 
@@ -3,20 +3,47 @@
 mod geoportal;
 mod mapbox;
 mod openstreetmap;
+mod proxied;
 
 use crate::mercator::TileId;
 pub use geoportal::Geoportal;
 pub use mapbox::{Mapbox, MapboxStyle};
 pub use openstreetmap::OpenStreetMap;
+pub use proxied::ProxiedSource;
 
 #[derive(Clone)]
 pub struct Attribution {
     pub text: &'static str,
     pub url: &'static str,
+
+    /// Logo to be displayed in the light theme.
     pub logo_light: Option<egui::ImageSource<'static>>,
+
+    /// Logo to be displayed in the dark theme.
     pub logo_dark: Option<egui::ImageSource<'static>>,
 }
 
+impl Attribution {
+    /// Logo matching the theme of the `visuals`, or the other one, if there is no dedicated
+    /// variant.
+    pub fn logo_source(&self, visuals: &egui::Visuals) -> Option<&egui::ImageSource<'static>> {
+        let (preferred, fallback) = if visuals.dark_mode {
+            (&self.logo_dark, &self.logo_light)
+        } else {
+            (&self.logo_light, &self.logo_dark)
+        };
+        preferred.as_ref().or(fallback.as_ref())
+    }
+
+    /// Logo matching the theme of the `visuals`, scaled to the given `height`, so logos of
+    /// different sources look consistent.
+    pub fn logo(&self, visuals: &egui::Visuals, height: f32) -> Option<egui::Image<'static>> {
+        self.logo_source(visuals).map(|source| {
+            egui::Image::new(source.clone()).fit_to_exact_size(egui::vec2(f32::INFINITY, height))
+        })
+    }
+}
+
 /// Remote tile server definition, source for the [`crate::HttpTiles`].
 pub trait TileSource {
     fn tile_url(&self, tile_id: TileId) -> String;
@@ -30,4 +57,92 @@ pub trait TileSource {
     fn max_zoom(&self) -> u8 {
         19
     }
+
+    /// URL of the tile rendered with twice the resolution, if the source provides such variant.
+    /// It is used instead of [`TileSource::tile_url`] on HiDPI displays, so the tiles stay crisp.
+    fn hidpi_tile_url(&self, _tile_id: TileId) -> Option<String> {
+        None
+    }
+}
+
+/// Pick one of the `subdomains` for the given tile, e.g. to spread the requests between
+/// `a.tile.example.com`, `b.tile.example.com`, etc. Same tile always gets the same subdomain, so it
+/// can be cached properly, while neighboring tiles get different ones.
+///
+/// Meant for custom [`TileSource`]s, as all the built-in ones are served from a single host.
+///
+/// # Panics
+///
+/// If `subdomains` is empty.
+pub fn subdomain<'a>(tile_id: TileId, subdomains: &[&'a str]) -> &'a str {
+    subdomains[(tile_id.x as usize + tile_id.y as usize) % subdomains.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attribution(
+        logo_light: Option<&'static str>,
+        logo_dark: Option<&'static str>,
+    ) -> Attribution {
+        Attribution {
+            text: "",
+            url: "",
+            logo_light: logo_light.map(|uri| egui::ImageSource::Uri(uri.into())),
+            logo_dark: logo_dark.map(|uri| egui::ImageSource::Uri(uri.into())),
+        }
+    }
+
+    #[test]
+    fn logo_matching_the_theme() {
+        let both = attribution(Some("light"), Some("dark"));
+        let uri = |attribution: &Attribution, visuals: egui::Visuals| {
+            attribution
+                .logo_source(&visuals)
+                .and_then(|source| source.uri())
+                .map(str::to_owned)
+        };
+
+        assert_eq!(Some("dark".into()), uri(&both, egui::Visuals::dark()));
+        assert_eq!(Some("light".into()), uri(&both, egui::Visuals::light()));
+
+        let light_only = attribution(Some("light"), None);
+        assert_eq!(
+            Some("light".into()),
+            uri(&light_only, egui::Visuals::dark())
+        );
+
+        let dark_only = attribution(None, Some("dark"));
+        assert_eq!(Some("dark".into()), uri(&dark_only, egui::Visuals::light()));
+
+        assert_eq!(None, uri(&attribution(None, None), egui::Visuals::dark()));
+    }
+
+    #[test]
+    fn logo_is_scaled_to_height() {
+        let logo = attribution(Some("light"), None)
+            .logo(&egui::Visuals::light(), 30.)
+            .unwrap();
+        let size = logo.calc_size(egui::Vec2::INFINITY, Some(egui::vec2(400., 100.)));
+        assert!((size - egui::vec2(120., 30.)).length() < 0.001, "{size:?}");
+    }
+
+    #[test]
+    fn neighboring_tiles_get_different_subdomains() {
+        let subdomains = ["a", "b", "c"];
+        let tile = |x, y| TileId { x, y, zoom: 10 };
+
+        assert_eq!("a", subdomain(tile(0, 0), &subdomains));
+        assert_eq!("b", subdomain(tile(1, 0), &subdomains));
+        assert_eq!("c", subdomain(tile(2, 0), &subdomains));
+        assert_eq!("a", subdomain(tile(3, 0), &subdomains));
+        assert_eq!("b", subdomain(tile(0, 1), &subdomains));
+
+        // Always the same for the same tile.
+        assert_eq!(
+            subdomain(tile(512, 341), &subdomains),
+            subdomain(tile(512, 341), &subdomains)
+        );
+    }
 }
@@ -2,6 +2,9 @@ use super::{Attribution, TileSource};
 use crate::TileId;
 
 /// <https://www.openstreetmap.org/about>
+///
+/// Tiles are downloaded from a single host, because `a`, `b` and `c` subdomains are deprecated
+/// by the OpenStreetMap. For servers which still use them, see [`super::subdomain`].
 pub struct OpenStreetMap;
 
 impl TileSource for OpenStreetMap {
@@ -0,0 +1,93 @@
+use super::{Attribution, TileSource};
+use crate::TileId;
+
+/// Wraps another [`TileSource`], rewriting its URLs, e.g. to route the traffic through a proxy or
+/// a CDN. Everything else is taken from the wrapped source.
+///
+/// ```
+/// # use walkers::sources::{OpenStreetMap, ProxiedSource};
+/// let source = ProxiedSource::new(OpenStreetMap, |url| {
+///     url.replace("https://tile.openstreetmap.org", "https://proxy.example.com/osm")
+/// });
+/// ```
+pub struct ProxiedSource<S, F> {
+    source: S,
+    rewrite: F,
+}
+
+impl<S: TileSource, F: Fn(String) -> String> ProxiedSource<S, F> {
+    /// Pass each URL of the `source` through the `rewrite` function.
+    pub fn new(source: S, rewrite: F) -> Self {
+        Self { source, rewrite }
+    }
+}
+
+impl<S: TileSource, F: Fn(String) -> String> TileSource for ProxiedSource<S, F> {
+    fn tile_url(&self, tile_id: TileId) -> String {
+        (self.rewrite)(self.source.tile_url(tile_id))
+    }
+
+    fn attribution(&self) -> Attribution {
+        self.source.attribution()
+    }
+
+    fn tile_size(&self) -> u32 {
+        self.source.tile_size()
+    }
+
+    fn max_zoom(&self) -> u8 {
+        self.source.max_zoom()
+    }
+
+    fn hidpi_tile_url(&self, tile_id: TileId) -> Option<String> {
+        self.source.hidpi_tile_url(tile_id).map(&self.rewrite)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::{Mapbox, OpenStreetMap};
+
+    static TILE_ID: TileId = TileId {
+        x: 1,
+        y: 2,
+        zoom: 3,
+    };
+
+    #[test]
+    fn urls_are_rewritten() {
+        let source = ProxiedSource::new(OpenStreetMap, |url| {
+            format!("https://proxy.example.com/?url={url}")
+        });
+
+        assert_eq!(
+            "https://proxy.example.com/?url=https://tile.openstreetmap.org/3/1/2.png",
+            source.tile_url(TILE_ID)
+        );
+        assert_eq!(None, source.hidpi_tile_url(TILE_ID));
+        assert_eq!(OpenStreetMap.attribution().text, source.attribution().text);
+        assert_eq!(OpenStreetMap.max_zoom(), source.max_zoom());
+    }
+
+    #[test]
+    fn hidpi_urls_are_rewritten() {
+        let source = ProxiedSource::new(
+            Mapbox {
+                access_token: "token".to_string(),
+                ..Default::default()
+            },
+            |url| url.replace("https://api.mapbox.com", "https://cdn.example.com"),
+        );
+
+        assert_eq!(
+            "https://cdn.example.com/styles/v1/mapbox/streets-v12/tiles/512/3/1/2?access_token=token",
+            source.tile_url(TILE_ID)
+        );
+        assert_eq!(
+            Some("https://cdn.example.com/styles/v1/mapbox/streets-v12/tiles/512/3/1/2@2x?access_token=token".to_string()),
+            source.hidpi_tile_url(TILE_ID)
+        );
+        assert_eq!(512, source.tile_size());
+    }
+}
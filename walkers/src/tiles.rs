@@ -1,48 +1,114 @@
-use egui::{pos2, Color32, Context, Mesh, Rect, Vec2};
-use egui::{ColorImage, TextureHandle};
-use futures::channel::mpsc::{channel, Receiver, Sender, TrySendError};
+use egui::{pos2, Color32, Context, Mesh, Pos2, Rect, Vec2};
+use egui::{ColorImage, TextureHandle, TextureOptions};
+use futures::channel::mpsc::{channel, Receiver, Sender};
 use image::ImageError;
 use lru::LruCache;
+use std::collections::HashMap;
+use std::sync::Arc;
 
-use crate::download::{download_continuously, HttpOptions, MAX_PARALLEL_DOWNLOADS};
+use crate::download::{download_continuously, HttpOptions, TileRequest, MAX_PARALLEL_DOWNLOADS};
 use crate::io::Runtime;
-use crate::mercator::TileId;
+use crate::mercator::{project, tile_id, TileId};
 use crate::sources::{Attribution, TileSource};
+use crate::Position;
 
 pub(crate) fn rect(screen_position: Vec2, tile_size: f64) -> Rect {
     Rect::from_min_size(screen_position.to_pos2(), Vec2::splat(tile_size as f32))
 }
 
+/// Decode PNG or JPEG image.
+pub(crate) fn decode(image: &[u8]) -> Result<ColorImage, ImageError> {
+    let image = image::load_from_memory(image)?.to_rgba8();
+    let pixels = image.as_flat_samples();
+    Ok(ColorImage::from_rgba_unmultiplied(
+        [image.width() as _, image.height() as _],
+        pixels.as_slice(),
+    ))
+}
+
+/// Some tile servers respond with a fully transparent image if they have no data.
+pub(crate) fn is_transparent(image: &ColorImage) -> bool {
+    image.pixels.iter().all(|pixel| pixel.a() == 0)
+}
+
+/// Shrink the `uv` by half of a texel of a texture of `texture_size`. Otherwise, linear filtering
+/// at the tile's edges blends in the texels beyond them, showing as seams between the tiles.
+pub(crate) fn inset_by_half_texel(uv: Rect, texture_size: Vec2) -> Rect {
+    uv.shrink2(Vec2::splat(0.5) / texture_size)
+}
+
 #[derive(Clone)]
-pub struct Texture(TextureHandle);
+pub struct Texture {
+    handle: TextureHandle,
+
+    /// Decoded image, kept only if it needs to be read later.
+    pixels: Option<Arc<ColorImage>>,
+}
 
 impl Texture {
     pub fn new(image: &[u8], ctx: &Context) -> Result<Self, ImageError> {
-        let image = image::load_from_memory(image)?.to_rgba8();
-        let pixels = image.as_flat_samples();
-        let image = ColorImage::from_rgba_unmultiplied(
-            [image.width() as _, image.height() as _],
-            pixels.as_slice(),
-        );
-
-        Ok(Self::from_color_image(image, ctx))
+        Ok(Self::from_color_image(decode(image)?, ctx))
     }
 
     /// Load the texture from egui's [`ColorImage`].
     pub fn from_color_image(color_image: ColorImage, ctx: &Context) -> Self {
-        Self(ctx.load_texture("image", color_image, Default::default()))
+        Self::from_color_image_with_options(color_image, ctx, Default::default())
+    }
+
+    /// Like [`Texture::from_color_image`], but with custom [`TextureOptions`], e.g.
+    /// [`TextureOptions::NEAREST`] for tiles whose exact pixel values matter.
+    pub fn from_color_image_with_options(
+        color_image: ColorImage,
+        ctx: &Context,
+        options: TextureOptions,
+    ) -> Self {
+        Self {
+            handle: ctx.load_texture("image", color_image, options),
+            pixels: None,
+        }
+    }
+
+    /// Like [`Texture::from_color_image_with_options`], but keeps the image, so it can be read
+    /// with [`Texture::pixel`].
+    pub(crate) fn from_color_image_keeping_pixels(
+        color_image: ColorImage,
+        ctx: &Context,
+        options: TextureOptions,
+    ) -> Self {
+        let pixels = Arc::new(color_image);
+        Self {
+            handle: ctx.load_texture("image", pixels.clone(), options),
+            pixels: Some(pixels),
+        }
+    }
+
+    /// Color at the given relative position, where `(0, 0)` is the top-left corner, and `(1, 1)`
+    /// the bottom-right one. Returns `None` if the image was not kept.
+    pub(crate) fn pixel(&self, position: Pos2) -> Option<Color32> {
+        let pixels = self.pixels.as_ref()?;
+        let x = (position.x * pixels.width() as f32) as usize;
+        let y = (position.y * pixels.height() as f32) as usize;
+        Some(
+            pixels[(
+                x.min(pixels.width().saturating_sub(1)),
+                y.min(pixels.height().saturating_sub(1)),
+            )],
+        )
     }
 
     pub(crate) fn size(&self) -> Vec2 {
-        self.0.size_vec2()
+        self.handle.size_vec2()
     }
 
     pub(crate) fn mesh_with_uv(&self, screen_position: Vec2, tile_size: f64, uv: Rect) -> Mesh {
-        self.mesh_with_rect_and_uv(rect(screen_position, tile_size), uv)
+        self.mesh_with_rect_and_uv(
+            rect(screen_position, tile_size),
+            inset_by_half_texel(uv, self.size()),
+        )
     }
 
     pub(crate) fn mesh_with_rect(&self, rect: Rect) -> Mesh {
-        let mut mesh = Mesh::with_texture(self.0.id());
+        let mut mesh = Mesh::with_texture(self.handle.id());
         mesh.add_rect_with_uv(
             rect,
             Rect::from_min_max(pos2(0., 0.0), pos2(1.0, 1.0)),
@@ -52,7 +118,7 @@ impl Texture {
     }
 
     pub(crate) fn mesh_with_rect_and_uv(&self, rect: Rect, uv: Rect) -> Mesh {
-        let mut mesh = Mesh::with_texture(self.0.id());
+        let mut mesh = Mesh::with_texture(self.handle.id());
         mesh.add_rect_with_uv(rect, uv, Color32::WHITE);
         mesh
     }
@@ -77,10 +143,20 @@ pub struct HttpTiles {
     cache: LruCache<TileId, Option<Texture>>,
 
     /// Tiles to be downloaded by the IO thread.
-    request_tx: Sender<TileId>,
+    request_tx: Sender<TileRequest>,
 
     /// Tiles that got downloaded and should be put in the cache.
-    tile_rx: Receiver<(TileId, Texture)>,
+    tile_rx: Receiver<(TileRequest, Texture)>,
+
+    /// Incremented by each invalidation. Requests are tagged with it, so downloads which were
+    /// already in flight when their tile got invalidated can be dropped.
+    generation: u64,
+
+    /// Generation in which a particular tile was last invalidated.
+    invalidated_at: HashMap<TileId, u64>,
+
+    /// Generation in which all tiles were last invalidated.
+    all_invalidated_at: u64,
 
     #[allow(dead_code)] // Significant Drop
     runtime: Runtime,
@@ -88,6 +164,13 @@ pub struct HttpTiles {
     tile_size: u32,
 
     max_zoom: u8,
+
+    /// See [`HttpOptions::max_texture_bytes`].
+    max_texture_bytes: Option<usize>,
+
+    /// Total size of the textures in the cache, kept up to date so it does not need to be summed
+    /// every time a tile arrives.
+    texture_bytes: usize,
 }
 
 impl HttpTiles {
@@ -112,6 +195,7 @@ impl HttpTiles {
         let attribution = source.attribution();
         let tile_size = source.tile_size();
         let max_zoom = source.max_zoom();
+        let max_texture_bytes = http_options.max_texture_bytes;
 
         let runtime = Runtime::new(download_continuously(
             source,
@@ -130,17 +214,81 @@ impl HttpTiles {
             cache: LruCache::new(cache_size),
             request_tx,
             tile_rx,
+            generation: 0,
+            invalidated_at: HashMap::new(),
+            all_invalidated_at: 0,
             runtime,
             tile_size,
             max_zoom,
+            max_texture_bytes,
+            texture_bytes: 0,
+        }
+    }
+
+    /// Color of the tile of given `zoom` at the `position`, e.g. to read the value of a data
+    /// raster. It is available only if the tile is already downloaded, and
+    /// [`HttpOptions::keep_pixels`] is set.
+    pub fn sample(&self, position: Position, zoom: u8) -> Option<Color32> {
+        let tile_id = tile_id(position, zoom, self.tile_size)?;
+        let texture = self.cache.peek(&tile_id)?.as_ref()?;
+
+        let offset = project(position, zoom as f64) - tile_id.project(self.tile_size as f64);
+        let tile_size = self.tile_size as f64;
+        texture.pixel(pos2(
+            (offset.x() / tile_size) as f32,
+            (offset.y() / tile_size) as f32,
+        ))
+    }
+
+    /// Forget the tile, so it is downloaded again next time it is needed. Keep in mind that the
+    /// response might still come from the HTTP cache, if [`HttpOptions::cache`] is set.
+    pub fn invalidate(&mut self, tile_id: TileId) {
+        if let Some(tile) = self.cache.pop(&tile_id) {
+            self.texture_bytes -= texture_bytes(&tile);
         }
+        self.generation += 1;
+        self.invalidated_at.insert(tile_id, self.generation);
+    }
+
+    /// Forget all tiles, so they are downloaded again next time they are needed.
+    pub fn invalidate_all(&mut self) {
+        self.cache.clear();
+        self.texture_bytes = 0;
+        self.generation += 1;
+        self.all_invalidated_at = self.generation;
+        self.invalidated_at.clear();
+    }
+
+    /// Whether the tile was invalidated after it had been requested. Also, if a fresher download
+    /// is already in the cache, as the invalidation is forgotten once it is stored.
+    fn is_stale(&self, request: &TileRequest) -> bool {
+        request.generation < self.all_invalidated_at
+            || self
+                .invalidated_at
+                .get(&request.tile_id)
+                .is_some_and(|invalidated_at| *invalidated_at > request.generation)
+            || matches!(self.cache.peek(&request.tile_id), Some(Some(_)))
     }
 
     fn put_single_downloaded_tile_in_cache(&mut self) {
         // This is called every frame, so take just one at the time.
         match self.tile_rx.try_next() {
-            Ok(Some((tile_id, tile))) => {
-                self.cache.put(tile_id, Some(tile));
+            Ok(Some((request, _))) if self.is_stale(&request) => {
+                log::debug!("Dropping {:?}, as it was invalidated.", request.tile_id);
+            }
+            Ok(Some((request, tile))) => {
+                let tile = Some(tile);
+                self.texture_bytes += texture_bytes(&tile);
+
+                // Either the same tile, or the least recently used one, if the cache is full.
+                if let Some((_, replaced)) = self.cache.push(request.tile_id, tile) {
+                    self.texture_bytes -= texture_bytes(&replaced);
+                }
+
+                // Downloaded after the invalidation, so there is no need to remember it anymore.
+                self.invalidated_at.remove(&request.tile_id);
+
+                self.evict_over_budget(request.tile_id);
             }
             Err(_) => {
                 // Just ignore. It means that no new tile was downloaded.
@@ -151,20 +299,56 @@ impl HttpTiles {
         }
     }
 
+    /// Evict least recently used tiles, until their textures fit in
+    /// [`HttpOptions::max_texture_bytes`]. Tiles which are still being downloaded take no space,
+    /// and the `newest` one is kept even if it alone exceeds the budget, as it is about to be shown.
+    fn evict_over_budget(&mut self, newest: TileId) {
+        let Some(max_texture_bytes) = self.max_texture_bytes else {
+            return;
+        };
+
+        while self.texture_bytes > max_texture_bytes {
+            let Some(tile_id) = self
+                .cache
+                .iter()
+                .rev()
+                .find(|(tile_id, tile)| **tile_id != newest && tile.is_some())
+                .map(|(tile_id, _)| *tile_id)
+            else {
+                break;
+            };
+
+            if let Some(tile) = self.cache.pop(&tile_id) {
+                log::trace!("Evicting {:?} to fit in the texture budget.", tile_id);
+                self.texture_bytes -= texture_bytes(&tile);
+            }
+        }
+    }
+
     fn make_sure_is_downloaded(&mut self, tile_id: TileId) {
-        if self
-            .cache
-            .try_get_or_insert(
-                tile_id,
-                || -> Result<Option<Texture>, TrySendError<TileId>> {
-                    self.request_tx.try_send(tile_id)?;
-                    log::trace!("Requested tile: {:?}", tile_id);
-                    Ok(None)
-                },
-            )
-            .is_err()
-        {
-            log::debug!("Request queue is full.");
+        let request = TileRequest {
+            tile_id,
+            generation: self.generation,
+        };
+
+        // Also marks the tile as recently used.
+        if self.cache.get(&tile_id).is_some() {
+            return;
+        }
+
+        match self.request_tx.try_send(request) {
+            Ok(()) => {
+                log::trace!("Requested tile: {:?}", tile_id);
+
+                // Pending tile takes the place of the least recently used one, if the cache is
+                // full.
+                if let Some((_, evicted)) = self.cache.push(tile_id, None) {
+                    self.texture_bytes -= texture_bytes(&evicted);
+                }
+            }
+            Err(_) => {
+                log::debug!("Request queue is full.");
+            }
         }
     }
 
@@ -188,6 +372,14 @@ impl HttpTiles {
     }
 }
 
+/// Estimated memory used by the tile's texture, assuming 4 bytes per pixel.
+fn texture_bytes(tile: &Option<Texture>) -> usize {
+    tile.as_ref().map_or(0, |texture| {
+        let size = texture.size();
+        size.x as usize * size.y as usize * 4
+    })
+}
+
 /// Take a piece of a tile with higher zoom level and use it as a tile with lower zoom level.
 fn interpolate_higher_zoom(tile_id: TileId, available_zoom: u8) -> (TileId, Rect) {
     assert!(tile_id.zoom >= available_zoom);
@@ -253,6 +445,35 @@ mod tests {
         zoom: 3,
     };
 
+    #[test]
+    fn uv_is_inset_by_half_texel() {
+        let uv = inset_by_half_texel(
+            Rect::from_min_max(pos2(0., 0.), pos2(1., 1.)),
+            Vec2::splat(256.),
+        );
+        assert_eq!(
+            Rect::from_min_max(
+                pos2(0.5 / 256., 0.5 / 256.),
+                pos2(255.5 / 256., 255.5 / 256.)
+            ),
+            uv
+        );
+
+        // Parts of the texture, used for interpolating higher zoom levels, are inset by the same
+        // amount.
+        let uv = inset_by_half_texel(
+            Rect::from_min_max(pos2(0.5, 0.25), pos2(0.75, 0.5)),
+            Vec2::splat(256.),
+        );
+        assert_eq!(
+            Rect::from_min_max(
+                pos2(128.5 / 256., 64.5 / 256.),
+                pos2(191.5 / 256., 127.5 / 256.)
+            ),
+            uv
+        );
+    }
+
     struct TestSource {
         base_url: String,
     }
@@ -325,6 +546,230 @@ mod tests {
         assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
     }
 
+    #[tokio::test]
+    async fn invalidated_tile_is_downloaded_again() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::new(source, Context::default());
+
+        server
+            .anticipate("/3/1/2.png")
+            .await
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+
+        let mut anticipated = server.anticipate("/3/1/2.png").await;
+        tiles.invalidate(TILE_ID);
+
+        // Initiate the download.
+        assert!(tiles.at(TILE_ID).is_none());
+
+        anticipated.expect().await;
+        anticipated
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+    }
+
+    #[tokio::test]
+    async fn tile_invalidated_while_downloading_is_not_used() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::with_options(
+            source,
+            HttpOptions {
+                keep_pixels: true,
+                ..Default::default()
+            },
+            Context::default(),
+        );
+        let position = crate::lon_lat(-120., 45.);
+
+        let mut stale = server.anticipate("/3/1/2.png").await;
+        assert!(tiles.at(TILE_ID).is_none());
+        stale.expect().await;
+
+        // Invalidate while the first download is still in flight, and request it again.
+        tiles.invalidate(TILE_ID);
+        let mut fresh = server.anticipate("/3/1/2.png").await;
+        assert!(tiles.at(TILE_ID).is_none());
+        fresh.expect().await;
+
+        stale.respond(solid_png([255, 0, 0, 255])).await;
+        for _ in 0..20 {
+            assert!(tiles.at(TILE_ID).is_none());
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        fresh.respond(solid_png([0, 0, 255, 255])).await;
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+        assert_eq!(Some(Color32::BLUE), tiles.sample(position, 3));
+        assert!(tiles.invalidated_at.is_empty());
+    }
+
+    #[tokio::test]
+    async fn tile_invalidated_while_downloading_is_not_used_even_if_it_comes_last() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::with_options(
+            source,
+            HttpOptions {
+                keep_pixels: true,
+                ..Default::default()
+            },
+            Context::default(),
+        );
+        let position = crate::lon_lat(-120., 45.);
+
+        let mut stale = server.anticipate("/3/1/2.png").await;
+        assert!(tiles.at(TILE_ID).is_none());
+        stale.expect().await;
+
+        tiles.invalidate(TILE_ID);
+        let mut fresh = server.anticipate("/3/1/2.png").await;
+        assert!(tiles.at(TILE_ID).is_none());
+        fresh.expect().await;
+
+        // Once the fresh one is stored, the invalidation is forgotten.
+        fresh.respond(solid_png([0, 0, 255, 255])).await;
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+        assert!(tiles.invalidated_at.is_empty());
+
+        stale.respond(solid_png([255, 0, 0, 255])).await;
+        for _ in 0..20 {
+            assert!(tiles.at(TILE_ID).is_some());
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(Some(Color32::BLUE), tiles.sample(position, 3));
+    }
+
+    #[tokio::test]
+    async fn cached_tile_is_not_downloaded_again() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::new(source, Context::default());
+
+        server
+            .anticipate("/3/1/2.png")
+            .await
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+
+        // Anticipate again, so any further request would be noticed.
+        let _anticipated = server.anticipate("/3/1/2.png").await;
+
+        assert!(tiles.at(TILE_ID).is_some());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(tiles.at(TILE_ID).is_some());
+
+        server.assert_not_requested("/3/1/2.png");
+    }
+
+    #[tokio::test]
+    async fn tiles_exceeding_texture_budget_are_evicted() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::with_options(
+            source,
+            HttpOptions {
+                // Enough for two 256x256 tiles.
+                max_texture_bytes: Some(2 * 256 * 256 * 4),
+                ..Default::default()
+            },
+            Context::default(),
+        );
+
+        let tile_ids = [TILE_ID, TILE_ID.east().unwrap(), TILE_ID.south().unwrap()];
+
+        for tile_id in tile_ids {
+            server
+                .anticipate(format!("/3/{}/{}.png", tile_id.x, tile_id.y))
+                .await
+                .respond(include_bytes!("../assets/blank-255-tile.png"))
+                .await;
+            assert_tile_to_become_available_eventually(&mut tiles, tile_id).await;
+        }
+
+        // The oldest one is gone.
+        assert!(!tiles.cache.contains(&tile_ids[0]));
+        assert!(tiles.cache.contains(&tile_ids[1]));
+        assert!(tiles.cache.contains(&tile_ids[2]));
+        assert_eq!(2 * 256 * 256 * 4, tiles.texture_bytes);
+    }
+
+    #[tokio::test]
+    async fn full_request_queue_does_not_evict_cached_tiles() {
+        let _ = env_logger::try_init();
+
+        let (_server, source) = hypermocker_mock().await;
+        let ctx = Context::default();
+        let mut tiles = HttpTiles::new(source, ctx.clone());
+
+        // Fill the cache up to its capacity.
+        let capacity = tiles.cache.cap().get();
+        for x in 0..capacity as u32 {
+            let texture = Texture::from_color_image(ColorImage::new([1, 1], Color32::RED), &ctx);
+            tiles.cache.put(TileId { x, y: 0, zoom: 10 }, Some(texture));
+            tiles.texture_bytes += 4;
+        }
+
+        // Request queue which is already full, and never consumed.
+        let (mut request_tx, _request_rx) = channel(0);
+        request_tx
+            .try_send(TileRequest {
+                tile_id: TILE_ID,
+                generation: 0,
+            })
+            .unwrap();
+        tiles.request_tx = request_tx;
+
+        tiles.make_sure_is_downloaded(TILE_ID);
+
+        assert_eq!(capacity, tiles.cache.len());
+        assert!(!tiles.cache.contains(&TILE_ID));
+        assert_eq!(capacity * 4, tiles.texture_bytes);
+    }
+
+    #[tokio::test]
+    async fn pending_and_newest_tiles_are_not_evicted() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::with_options(
+            source,
+            HttpOptions {
+                // Not enough even for a single tile.
+                max_texture_bytes: Some(1),
+                ..Default::default()
+            },
+            Context::default(),
+        );
+
+        // Still being downloaded when the other tile arrives.
+        let pending = TILE_ID.east().unwrap();
+        let mut anticipated = server.anticipate("/3/2/2.png").await;
+        assert!(tiles.at(pending).is_none());
+        anticipated.expect().await;
+
+        server
+            .anticipate("/3/1/2.png")
+            .await
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+
+        assert!(tiles.cache.contains(&pending));
+        assert!(tiles.at(TILE_ID).is_some());
+        assert_eq!(256 * 256 * 4, tiles.texture_bytes);
+    }
+
     #[tokio::test]
     async fn custom_user_agent_header() {
         let _ = env_logger::try_init();
@@ -335,8 +780,8 @@ mod tests {
         let mut tiles = HttpTiles::with_options(
             source,
             HttpOptions {
-                cache: None,
                 user_agent: Some(crate::HeaderValue::from_static("MyApp")),
+                ..Default::default()
             },
             Context::default(),
         );
@@ -359,7 +804,7 @@ mod tests {
         let mut tiles = HttpTiles::new(source, Context::default());
 
         // First download is started immediately.
-        let mut first_outstanding_request = server.anticipate(format!("/3/1/2.png")).await;
+        let mut first_outstanding_request = server.anticipate("/3/1/2.png").await;
         assert!(tiles.at(TILE_ID).is_none());
         first_outstanding_request.expect().await;
 
@@ -450,6 +895,282 @@ mod tests {
         assert_tile_is_empty_forever(&mut tiles).await;
     }
 
+    /// Middleware which remembers URLs of all requests passing through it.
+    #[derive(Default)]
+    struct ObservingMiddleware {
+        urls: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl reqwest_middleware::Middleware for ObservingMiddleware {
+        async fn handle(
+            &self,
+            request: reqwest::Request,
+            extensions: &mut task_local_extensions::Extensions,
+            next: reqwest_middleware::Next<'_>,
+        ) -> reqwest_middleware::Result<reqwest::Response> {
+            self.urls.lock().unwrap().push(request.url().to_string());
+            next.run(request, extensions).await
+        }
+    }
+
+    #[tokio::test]
+    async fn custom_middleware_observes_requests() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let url = source.tile_url(TILE_ID);
+        let anticipated = server.anticipate("/3/1/2.png").await;
+        let middleware = std::sync::Arc::new(ObservingMiddleware::default());
+
+        let mut tiles = HttpTiles::with_options(
+            source,
+            HttpOptions {
+                middleware: vec![middleware.clone()],
+                ..Default::default()
+            },
+            Context::default(),
+        );
+
+        anticipated
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+
+        assert_eq!(vec![url], *middleware.urls.lock().unwrap());
+    }
+
+    fn solid_png(color: [u8; 4]) -> Vec<u8> {
+        let mut png = Vec::new();
+        image::RgbaImage::from_pixel(256, 256, image::Rgba(color))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
+    fn transparent_png() -> Vec<u8> {
+        solid_png([0, 0, 0, 0])
+    }
+
+    #[tokio::test]
+    async fn sampling_downloaded_tile() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::with_options(
+            source,
+            HttpOptions {
+                keep_pixels: true,
+                ..Default::default()
+            },
+            Context::default(),
+        );
+        server
+            .anticipate("/3/1/2.png")
+            .await
+            .respond(solid_png([255, 0, 0, 255]))
+            .await;
+
+        // Somewhere within the tile 1/2 at zoom 3.
+        let position = crate::lon_lat(-120., 45.);
+        assert_eq!(None, tiles.sample(position, 3));
+
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+        assert_eq!(Some(Color32::RED), tiles.sample(position, 3));
+
+        // Tiles which are not downloaded cannot be sampled.
+        assert_eq!(None, tiles.sample(crate::lon_lat(120., 45.), 3));
+    }
+
+    #[tokio::test]
+    async fn tiles_with_nearest_neighbor_filtering() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let ctx = Context::default();
+        let mut tiles = HttpTiles::with_options(
+            source,
+            HttpOptions {
+                texture_options: TextureOptions::NEAREST,
+                ..Default::default()
+            },
+            ctx.clone(),
+        );
+        server
+            .anticipate("/3/1/2.png")
+            .await
+            .respond(solid_png([255, 0, 0, 255]))
+            .await;
+
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+
+        let texture = tiles.at(TILE_ID).unwrap().texture;
+        let meta = ctx.tex_manager().read().meta(texture.handle.id()).cloned();
+        assert_eq!(TextureOptions::NEAREST, meta.unwrap().options);
+    }
+
+    #[tokio::test]
+    async fn sampling_requires_keeping_pixels() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::new(source, Context::default());
+        server
+            .anticipate("/3/1/2.png")
+            .await
+            .respond(solid_png([255, 0, 0, 255]))
+            .await;
+
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+        assert_eq!(None, tiles.sample(crate::lon_lat(-120., 45.), 3));
+    }
+
+    #[test]
+    fn detecting_transparent_images() {
+        assert!(is_transparent(&decode(&transparent_png()).unwrap()));
+        assert!(!is_transparent(
+            &decode(include_bytes!("../assets/blank-255-tile.png")).unwrap()
+        ));
+    }
+
+    #[tokio::test]
+    async fn custom_decoder_is_used() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::with_options(
+            source,
+            HttpOptions {
+                keep_pixels: true,
+                decoder: Some(Arc::new(|bytes: &[u8]| {
+                    // Format which is only the color of the tile.
+                    let [r, g, b] = bytes.try_into()?;
+                    Ok(ColorImage::new([256, 256], Color32::from_rgb(r, g, b)))
+                })),
+                ..Default::default()
+            },
+            Context::default(),
+        );
+        server
+            .anticipate("/3/1/2.png")
+            .await
+            .respond(vec![0, 0, 255])
+            .await;
+
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+        assert_eq!(
+            Some(Color32::BLUE),
+            tiles.sample(crate::lon_lat(-120., 45.), 3)
+        );
+    }
+
+    #[tokio::test]
+    async fn tile_is_empty_forever_if_custom_decoder_fails() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::with_options(
+            source,
+            HttpOptions {
+                decoder: Some(Arc::new(|_: &[u8]| Err("unsupported format".into()))),
+                ..Default::default()
+            },
+            Context::default(),
+        );
+        server
+            .anticipate("/3/1/2.png")
+            .await
+            .respond(solid_png([255, 0, 0, 255]))
+            .await;
+
+        assert_tile_is_empty_forever(&mut tiles).await;
+    }
+
+    #[tokio::test]
+    async fn transparent_tile_is_used_by_default() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::new(source, Context::default());
+        server
+            .anticipate("/3/1/2.png")
+            .await
+            .respond(transparent_png())
+            .await;
+
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+    }
+
+    #[tokio::test]
+    async fn transparent_tile_is_empty_forever_if_skipped() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::with_options(
+            source,
+            HttpOptions {
+                skip_transparent_tiles: true,
+                ..Default::default()
+            },
+            Context::default(),
+        );
+        server
+            .anticipate("/3/1/2.png")
+            .await
+            .respond(transparent_png())
+            .await;
+
+        assert_tile_is_empty_forever(&mut tiles).await;
+    }
+
+    #[tokio::test]
+    async fn skipped_transparent_tile_is_substituted_by_lower_zoom() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::with_options(
+            source,
+            HttpOptions {
+                skip_transparent_tiles: true,
+                keep_pixels: true,
+                ..Default::default()
+            },
+            Context::default(),
+        );
+
+        // Lower zoom tile containing the TILE_ID.
+        let parent = TileId {
+            x: 0,
+            y: 1,
+            zoom: 2,
+        };
+        server
+            .anticipate("/2/0/1.png")
+            .await
+            .respond(solid_png([255, 0, 0, 255]))
+            .await;
+        assert_tile_to_become_available_eventually(&mut tiles, parent).await;
+
+        let mut anticipated = server.anticipate("/3/1/2.png").await;
+        assert!(tiles.at(TILE_ID).is_some());
+        anticipated.expect().await;
+        anticipated.respond(transparent_png()).await;
+
+        // Give the transparent tile a chance to arrive.
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        // Top-right quarter of the parent is drawn instead.
+        let substitute = tiles.at(TILE_ID).unwrap();
+        assert_eq!(
+            Rect::from_min_max(pos2(0.5, 0.), pos2(1., 0.5)),
+            substitute.uv
+        );
+        assert_eq!(
+            Some(Color32::RED),
+            substitute.texture.pixel(substitute.uv.center())
+        );
+    }
+
     /// Tile source, which gives invalid urls.
     struct GarbageSource;
 
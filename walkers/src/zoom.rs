@@ -1,6 +1,31 @@
-#[derive(thiserror::Error, Debug, PartialEq, Eq)]
-#[error("invalid zoom level")]
-pub struct InvalidZoom;
+/// Lowest zoom level, at which the whole world is a single tile.
+const MIN_ZOOM: f64 = 0.;
+
+/// Highest zoom level.
+const MAX_ZOOM: f64 = 26.;
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("invalid zoom level {zoom}, it must be between {min} and {max}")]
+pub struct InvalidZoom {
+    /// Zoom level which was attempted.
+    pub zoom: f64,
+
+    /// Lowest allowed zoom level.
+    pub min: f64,
+
+    /// Highest allowed zoom level.
+    pub max: f64,
+}
+
+impl InvalidZoom {
+    fn new(zoom: f64) -> Self {
+        Self {
+            zoom,
+            min: MIN_ZOOM,
+            max: MAX_ZOOM,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Copy)]
 pub(crate) struct Zoom(f64);
@@ -10,8 +35,8 @@ impl TryFrom<f64> for Zoom {
 
     fn try_from(value: f64) -> Result<Self, Self::Error> {
         // The upper limit is artificial. Should it be removed altogether?
-        if !(0. ..=26.).contains(&value) {
-            Err(InvalidZoom)
+        if !(MIN_ZOOM..=MAX_ZOOM).contains(&value) {
+            Err(InvalidZoom::new(value))
         } else {
             Ok(Self(value))
         }
@@ -63,7 +88,7 @@ mod tests {
     fn test_constructing_zoom() {
         assert_eq!(16, Zoom::default().round());
         assert_eq!(26, Zoom::try_from(26.).unwrap().round());
-        assert_eq!(InvalidZoom, Zoom::try_from(27.).unwrap_err());
+        assert_eq!(InvalidZoom::new(27.), Zoom::try_from(27.).unwrap_err());
     }
 
     #[test]
@@ -71,7 +96,7 @@ mod tests {
         let mut zoom = Zoom::try_from(25.).unwrap();
         assert!(zoom.zoom_in().is_ok());
         assert_eq!(26, zoom.round());
-        assert_eq!(Err(InvalidZoom), zoom.zoom_in());
+        assert_eq!(Err(InvalidZoom::new(27.)), zoom.zoom_in());
     }
 
     #[test]
@@ -79,6 +104,14 @@ mod tests {
         let mut zoom = Zoom::try_from(1.).unwrap();
         assert!(zoom.zoom_out().is_ok());
         assert_eq!(0, zoom.round());
-        assert_eq!(Err(InvalidZoom), zoom.zoom_out());
+        assert_eq!(Err(InvalidZoom::new(-1.)), zoom.zoom_out());
+    }
+
+    #[test]
+    fn invalid_zoom_message() {
+        assert_eq!(
+            "invalid zoom level 27.5, it must be between 0 and 26",
+            Zoom::try_from(27.5).unwrap_err().to_string()
+        );
     }
 }